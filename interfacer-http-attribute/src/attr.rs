@@ -1,23 +1,56 @@
-use crate::parse::AttrMeta;
+use crate::parse::{try_parse, AttrMeta};
+use http::header::HeaderName;
 use http::StatusCode;
 use mime::Mime;
 use proc_macro::{Diagnostic, Level};
 use proc_macro2::TokenStream;
 use quote::quote;
 use std::convert::{TryFrom, TryInto};
-use syn::{Lit, Meta, NestedMeta, TraitItemMethod};
+use syn::parse::{ParseStream, Parser};
+use syn::punctuated::Punctuated;
+use syn::{
+    parenthesized, Attribute, BinOp, Expr, ExprBinary, ExprLit, ExprPath, ExprRange, ItemTrait,
+    Lit, Meta, MetaNameValue, NestedMeta, RangeLimits, Token, TraitItemMethod,
+};
 
 const METHODS: [&str; 9] = [
     "get", "post", "put", "delete", "head", "options", "connect", "patch", "trace",
 ];
 const EXPECT: &str = "expect";
+const RETRY: &str = "retry";
+const TIMEOUT: &str = "timeout";
+const COMPRESS: &str = "compress";
+const ERROR_BODY: &str = "error_body";
+const AUTH: &str = "auth";
+const HEADER: &str = "header";
+const DEFAULT_API_KEY_HEADER: &str = "X-Api-Key";
 
 const DEFAULT_PATH: &str = "/";
+const SUCCESS: &str = "success";
 
 #[derive(Clone)]
 pub struct Expect {
     pub status: TokenStream,
-    pub content_type: Option<TokenStream>,
+    /// acceptable content types in order of preference.
+    pub content_types: Vec<TokenStream>,
+}
+
+/// type and content type to decode body of unexpected response.
+#[derive(Clone)]
+pub struct ErrorBody {
+    pub typ: TokenStream,
+    pub content_type: TokenStream,
+}
+
+/// scheme and header of `#[auth]`.
+#[derive(Clone)]
+pub enum Auth {
+    /// `#[auth(none)]`, disable auth of the trait.
+    None,
+    Scheme {
+        scheme: TokenStream,
+        header: TokenStream,
+    },
 }
 
 #[derive(Clone)]
@@ -30,33 +63,55 @@ pub struct Request {
 #[derive(Clone)]
 struct AttrMetas {
     pub req: AttrMeta,
-    pub expect: Option<AttrMeta>,
+    pub expect: Option<Attribute>,
+    pub retry: bool,
+    pub timeout: Option<AttrMeta>,
+    pub compress: Option<AttrMeta>,
+    pub error_body: Option<AttrMeta>,
+    pub auth: Option<AttrMeta>,
 }
 
 #[derive(Clone)]
 pub struct Attr {
     pub req: Request,
     pub expect: Expect,
+    pub retry: bool,
+    pub timeout: Option<TokenStream>,
+    /// encoding to compress request body.
+    pub compress: Option<TokenStream>,
+    pub error_body: Option<ErrorBody>,
+    pub auth: Option<Auth>,
 }
 
-impl TryFrom<AttrMeta> for Expect {
+// status of expect attribute is not a valid meta, like `200 | 204` or `2xx`,
+// so args are parsed as expressions.
+// the rest args are acceptable content types, like `expect(200, mime::APPLICATION_JSON, "application/msgpack")`.
+impl TryFrom<Attribute> for Expect {
     type Error = Diagnostic;
-    fn try_from(meta: AttrMeta) -> Result<Self, Self::Error> {
+    fn try_from(attr: Attribute) -> Result<Self, Self::Error> {
         let mut expect = Self::default();
-        if let AttrMeta::List { name: _, nested } = meta {
-            let metas = nested.into_iter().collect::<Vec<NestedMeta>>();
-            if metas.len() > 2 {
-                return Err(Diagnostic::new(
-                    Level::Error,
-                    "expect attribute has two args at most",
-                ));
-            }
-            if !metas.is_empty() {
-                expect.status = parse_status(&metas[0])?;
-            }
-            if metas.len() > 1 {
-                expect.content_type = Some(parse_content_type(&metas[1])?);
-            }
+        if attr.tokens.is_empty() {
+            return Ok(expect);
+        }
+        let parser = |input: ParseStream| {
+            let content;
+            parenthesized!(content in input);
+            Punctuated::<Expr, Token![,]>::parse_terminated(&content)
+        };
+        let args = parser.parse2(attr.tokens.clone()).map_err(|err| {
+            Diagnostic::new(
+                Level::Error,
+                format!("invalid expect attribute ({}): {}", quote!(#attr), err),
+            )
+        })?;
+        let mut args = args.into_iter();
+        if let Some(status) = args.next() {
+            expect.status = parse_status(&status)?;
+        }
+        for arg in args {
+            expect
+                .content_types
+                .push(parse_content_type(&try_parse(quote!(#arg))?)?);
         }
         Ok(expect)
     }
@@ -65,10 +120,129 @@ impl TryFrom<AttrMeta> for Expect {
 impl Default for Expect {
     fn default() -> Self {
         let code = StatusCode::OK.as_u16();
-        let status = quote!(StatusCode::from_u16(#code).unwrap());
+        let status = quote!(StatusSet::new(vec![#code..=#code]));
         Self {
             status,
-            content_type: None,
+            content_types: Vec::new(),
+        }
+    }
+}
+
+/// attributes of `http_service` trait, as defaults of each method.
+#[derive(Clone, Default)]
+pub struct ServiceAttr {
+    pub error_body: Option<ErrorBody>,
+    pub auth: Option<Auth>,
+}
+
+impl ServiceAttr {
+    pub fn from_raw(raw_trait: &ItemTrait) -> Result<ServiceAttr, Diagnostic> {
+        let duplicate = || {
+            Diagnostic::new(
+                Level::Error,
+                format!("trait `{}` has duplicate attribute", raw_trait.ident),
+            )
+        };
+        let mut service = Self::default();
+        for attr in raw_trait.attrs.iter() {
+            if let Ok(meta) = AttrMeta::try_from((*attr).clone()) {
+                if meta.name() == ERROR_BODY {
+                    if service.error_body.is_some() {
+                        return Err(duplicate());
+                    }
+                    service.error_body = Some(meta.try_into()?)
+                } else if meta.name() == AUTH {
+                    if service.auth.is_some() {
+                        return Err(duplicate());
+                    }
+                    service.auth = Some(meta.try_into()?)
+                }
+            }
+        }
+        Ok(service)
+    }
+}
+
+// `#[auth(bearer)]`, `#[auth(basic)]`, `#[auth(api_key, header = "X-Api-Key")]` or `#[auth(none)]`,
+// bearer and basic are sent by `Authorization` by default.
+impl TryFrom<AttrMeta> for Auth {
+    type Error = Diagnostic;
+    fn try_from(meta: AttrMeta) -> Result<Self, Self::Error> {
+        let err = || {
+            Diagnostic::new(
+                Level::Error,
+                "auth attribute should be like `#[auth(bearer)]`, `#[auth(basic)]`, `#[auth(api_key, header = \"X-Api-Key\")]` or `#[auth(none)]`",
+            )
+        };
+        let metas = match meta {
+            AttrMeta::List { name: _, nested } => nested.into_iter().collect::<Vec<NestedMeta>>(),
+            _ => return Err(err()),
+        };
+        let (scheme, options) = match metas.split_first() {
+            Some((NestedMeta::Meta(Meta::Path(scheme)), options)) => (scheme, options),
+            _ => return Err(err()),
+        };
+        let mut header = None;
+        for option in options {
+            match option {
+                NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+                    path,
+                    lit: Lit::Str(value),
+                    ..
+                })) if path.is_ident(HEADER) && header.is_none() => {
+                    let value = value.value();
+                    HeaderName::from_bytes(value.as_bytes()).map_err(|err| {
+                        Diagnostic::new(
+                            Level::Error,
+                            format!("invalid header name('{}'): {}", value, err),
+                        )
+                    })?;
+                    header = Some(quote!(#value))
+                }
+                _ => return Err(err()),
+            }
+        }
+        let authorization = quote!(interfacer_http::http::header::AUTHORIZATION);
+        let (scheme, default_header) = if scheme.is_ident("bearer") {
+            (quote!(interfacer_http::AuthScheme::Bearer), authorization)
+        } else if scheme.is_ident("basic") {
+            (quote!(interfacer_http::AuthScheme::Basic), authorization)
+        } else if scheme.is_ident("api_key") {
+            (
+                quote!(interfacer_http::AuthScheme::ApiKey),
+                quote!(#DEFAULT_API_KEY_HEADER),
+            )
+        } else if scheme.is_ident("none") && header.is_none() {
+            return Ok(Auth::None);
+        } else {
+            return Err(err());
+        };
+        Ok(Auth::Scheme {
+            scheme,
+            header: header.unwrap_or(default_header),
+        })
+    }
+}
+
+impl TryFrom<AttrMeta> for ErrorBody {
+    type Error = Diagnostic;
+    fn try_from(meta: AttrMeta) -> Result<Self, Self::Error> {
+        let err = || {
+            Diagnostic::new(
+                Level::Error,
+                "error_body attribute should be like `#[error_body(ErrorType, mime::APPLICATION_JSON)]`",
+            )
+        };
+        let metas = match meta {
+            AttrMeta::List { name: _, nested } => nested.into_iter().collect::<Vec<NestedMeta>>(),
+            _ => return Err(err()),
+        };
+        match metas.as_slice() {
+            [NestedMeta::Meta(Meta::Path(typ)), content_type] => Ok(Self {
+                typ: quote!(#typ),
+                content_type: parse_content_type(content_type)?,
+            }),
+            _ => Err(err()),
         }
     }
 }
@@ -112,13 +286,45 @@ impl TryFrom<AttrMeta> for Request {
 
 impl Attr {
     pub fn from_raw(raw_method: &TraitItemMethod) -> Result<Attr, Diagnostic> {
-        let AttrMetas { req, mut expect } = filter_method(raw_method)?;
+        let AttrMetas {
+            req,
+            mut expect,
+            retry,
+            timeout,
+            compress,
+            error_body,
+            auth,
+        } = filter_method(raw_method)?;
         let expect = match expect.take() {
             Some(meta) => meta.try_into()?,
             None => Default::default(),
         };
         let req = req.try_into()?;
-        Ok(Attr { req, expect })
+        let timeout = match timeout {
+            Some(meta) => Some(parse_timeout(meta)?),
+            None => None,
+        };
+        let compress = match compress {
+            Some(meta) => Some(parse_compress(meta)?),
+            None => None,
+        };
+        let error_body = match error_body {
+            Some(meta) => Some(meta.try_into()?),
+            None => None,
+        };
+        let auth = match auth {
+            Some(meta) => Some(meta.try_into()?),
+            None => None,
+        };
+        Ok(Attr {
+            req,
+            expect,
+            retry,
+            timeout,
+            compress,
+            error_body,
+            auth,
+        })
     }
 }
 
@@ -153,27 +359,159 @@ fn parse_content_type(meta: &NestedMeta) -> Result<TokenStream, Diagnostic> {
     }
 }
 
-fn parse_status(meta: &NestedMeta) -> Result<TokenStream, Diagnostic> {
-    match meta {
-        NestedMeta::Lit(Lit::Int(lit)) => {
-            StatusCode::from_u16(lit.base10_digits().parse().unwrap())
-                .map(|code| {
-                    let code = code.as_u16();
-                    quote!(StatusCode::from_u16(#code).unwrap())
-                })
-                .map_err(|err| {
-                    Diagnostic::new(Level::Error, format!("invalid status code: {}", err))
-                })
+/// parse status set like `200`, `StatusCode::OK`, `200 | 204`, `2xx`, `200..=299` or `success`,
+/// ranges in a list should be parenthesized, like `(200..=204) | 304`.
+fn parse_status(expr: &Expr) -> Result<TokenStream, Diagnostic> {
+    let mut ranges = Vec::new();
+    collect_status_ranges(expr, &mut ranges)?;
+    Ok(quote!(StatusSet::new(vec![#(#ranges),*])))
+}
+
+fn collect_status_ranges(expr: &Expr, ranges: &mut Vec<TokenStream>) -> Result<(), Diagnostic> {
+    match expr {
+        Expr::Binary(ExprBinary {
+            left,
+            op: BinOp::BitOr(_),
+            right,
+            ..
+        }) => {
+            collect_status_ranges(left, ranges)?;
+            collect_status_ranges(right, ranges)?;
+        }
+        Expr::Paren(paren) => collect_status_ranges(&paren.expr, ranges)?,
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) if lit.suffix() == "xx" => {
+            let class: u16 = lit.base10_parse().map_err(invalid_status)?;
+            if !(1..=5).contains(&class) {
+                return Err(Diagnostic::new(
+                    Level::Error,
+                    format!("invalid status class: {}xx", class),
+                ));
+            }
+            let (start, end) = (class * 100, class * 100 + 99);
+            ranges.push(quote!(#start..=#end));
+        }
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(_), ..
+        }) => {
+            let code = parse_status_code(expr)?;
+            ranges.push(quote!(#code..=#code));
+        }
+        Expr::Range(ExprRange {
+            from: Some(from),
+            limits,
+            to: Some(to),
+            ..
+        }) => {
+            let start = parse_status_code(from)?;
+            let end = match limits {
+                RangeLimits::Closed(_) => parse_status_code(to)?,
+                RangeLimits::HalfOpen(_) => parse_status_code(to)? - 1,
+            };
+            if start > end {
+                return Err(Diagnostic::new(
+                    Level::Error,
+                    format!("empty status range: {}", quote!(#expr)),
+                ));
+            }
+            ranges.push(quote!(#start..=#end));
+        }
+        Expr::Path(ExprPath { path, .. }) if path.is_ident(SUCCESS) => {
+            ranges.push(quote!(200u16..=299u16));
+        }
+        Expr::Path(ExprPath { path, .. }) => ranges.push(quote!({
+            let _code = #path.as_u16();
+            _code..=_code
+        })),
+        _ => {
+            return Err(Diagnostic::new(
+                Level::Error,
+                "status should be integer literal, path, range, `2xx` or `success`",
+            ))
+        }
+    }
+    Ok(())
+}
+
+fn parse_status_code(expr: &Expr) -> Result<u16, Diagnostic> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) if lit.suffix().is_empty() => {
+            let code = lit.base10_parse().map_err(invalid_status)?;
+            StatusCode::from_u16(code)
+                .map(|code| code.as_u16())
+                .map_err(invalid_status)
         }
-        NestedMeta::Meta(Meta::Path(path)) => Ok(quote!(#path)),
         _ => Err(Diagnostic::new(
             Level::Error,
-            "status should be string literal",
+            format!("status code should be integer literal: {}", quote!(#expr)),
         )),
     }
 }
 
-fn check_duplicate(method_name: &str, attr: &Option<AttrMeta>) -> Result<(), Diagnostic> {
+fn invalid_status(err: impl std::fmt::Display) -> Diagnostic {
+    Diagnostic::new(Level::Error, format!("invalid status code: {}", err))
+}
+
+fn parse_timeout(meta: AttrMeta) -> Result<TokenStream, Diagnostic> {
+    let err = || {
+        Diagnostic::new(
+            Level::Error,
+            "timeout attribute should be like `#[timeout(ms = 500)]` or `#[timeout(secs = 5)]`",
+        )
+    };
+    let nested = match meta {
+        AttrMeta::List { name: _, nested } if nested.len() == 1 => nested,
+        _ => return Err(err()),
+    };
+    match nested.first() {
+        Some(NestedMeta::Meta(Meta::NameValue(MetaNameValue {
+            path,
+            lit: Lit::Int(lit),
+            ..
+        }))) => {
+            let value: u64 = lit.base10_parse().map_err(|_| err())?;
+            if path.is_ident("ms") {
+                Ok(quote!(std::time::Duration::from_millis(#value)))
+            } else if path.is_ident("secs") {
+                Ok(quote!(std::time::Duration::from_secs(#value)))
+            } else {
+                Err(err())
+            }
+        }
+        _ => Err(err()),
+    }
+}
+
+// `#[compress(gzip)]`, `#[compress(deflate)]` or `#[compress(br)]`.
+fn parse_compress(meta: AttrMeta) -> Result<TokenStream, Diagnostic> {
+    let err = || {
+        Diagnostic::new(
+            Level::Error,
+            "compress attribute should be like `#[compress(gzip)]`, `#[compress(deflate)]` or `#[compress(br)]`",
+        )
+    };
+    let nested = match meta {
+        AttrMeta::List { name: _, nested } if nested.len() == 1 => nested,
+        _ => return Err(err()),
+    };
+    match nested.first() {
+        Some(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("gzip") => {
+            Ok(quote!(interfacer_http::ContentEncoding::Gzip))
+        }
+        Some(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("deflate") => {
+            Ok(quote!(interfacer_http::ContentEncoding::Deflate))
+        }
+        Some(NestedMeta::Meta(Meta::Path(path))) if path.is_ident("br") => {
+            Ok(quote!(interfacer_http::ContentEncoding::Brotli))
+        }
+        _ => Err(err()),
+    }
+}
+
+fn check_duplicate<T>(method_name: &str, attr: &Option<T>) -> Result<(), Diagnostic> {
     match attr {
         None => Ok(()),
         Some(_) => Err(Diagnostic::new(
@@ -187,11 +525,33 @@ fn filter_method(raw_method: &TraitItemMethod) -> Result<AttrMetas, Diagnostic>
     let method_name = raw_method.sig.ident.to_string();
     let mut req = None;
     let mut expect = None;
+    let mut retry = false;
+    let mut timeout = None;
+    let mut compress = None;
+    let mut error_body = None;
+    let mut auth = None;
     for attr in raw_method.attrs.iter() {
-        if let Ok(meta) = AttrMeta::try_from((*attr).clone()) {
-            if meta.name() == EXPECT {
-                check_duplicate(method_name.as_str(), &expect)?;
-                expect = Some(meta)
+        if attr.path.is_ident(EXPECT) {
+            check_duplicate(method_name.as_str(), &expect)?;
+            expect = Some(attr.clone())
+        } else if let Ok(meta) = AttrMeta::try_from((*attr).clone()) {
+            if meta.name() == ERROR_BODY {
+                check_duplicate(method_name.as_str(), &error_body)?;
+                error_body = Some(meta)
+            } else if meta.name() == AUTH {
+                check_duplicate(method_name.as_str(), &auth)?;
+                auth = Some(meta)
+            } else if meta.name() == TIMEOUT {
+                check_duplicate(method_name.as_str(), &timeout)?;
+                timeout = Some(meta)
+            } else if meta.name() == COMPRESS {
+                check_duplicate(method_name.as_str(), &compress)?;
+                compress = Some(meta)
+            } else if meta.name() == RETRY {
+                if let AttrMeta::List { .. } = meta {
+                    return Err(Diagnostic::new(Level::Error, "retry attribute has no args"));
+                }
+                retry = true
             } else if METHODS.contains(&meta.name().to_string().as_str()) {
                 check_duplicate(method_name.as_str(), &req)?;
                 req = Some(meta)
@@ -200,7 +560,15 @@ fn filter_method(raw_method: &TraitItemMethod) -> Result<AttrMetas, Diagnostic>
     }
 
     match req {
-        Some(req) => Ok(AttrMetas { req, expect }),
+        Some(req) => Ok(AttrMetas {
+            req,
+            expect,
+            retry,
+            timeout,
+            compress,
+            error_body,
+            auth,
+        }),
         None => Err(Diagnostic::new(
             Level::Error,
             format!("method `{}` has no request attribute", method_name,),
@@ -210,13 +578,13 @@ fn filter_method(raw_method: &TraitItemMethod) -> Result<AttrMetas, Diagnostic>
 
 #[cfg(test)]
 mod tests {
-    use super::{Attr, DEFAULT_PATH};
+    use super::{Attr, Auth, ServiceAttr, DEFAULT_PATH};
     use quote::quote;
     use syn::parse_quote;
 
     #[test]
     fn default() {
-        let Attr { req, expect } = Attr::from_raw(&parse_quote!(
+        let Attr { req, expect, .. } = Attr::from_raw(&parse_quote!(
             #[get]
             fn a(&self);
         ))
@@ -224,16 +592,16 @@ mod tests {
         assert_eq!("GET", &req.method);
         assert_eq!(DEFAULT_PATH, &req.path);
         assert_eq!(
-            quote!(StatusCode::from_u16(200u16).unwrap()).to_string(),
+            quote!(StatusSet::new(vec![200u16..=200u16])).to_string(),
             expect.status.to_string()
         );
         assert!(req.content_type.is_none());
-        assert!(expect.content_type.is_none());
+        assert!(expect.content_types.is_empty());
     }
 
     #[test]
     fn full() {
-        let Attr { req, expect } = Attr::from_raw(&parse_quote!(
+        let Attr { req, expect, .. } = Attr::from_raw(&parse_quote!(
             #[post("api/user", mime::APPLICATION_JSON)]
             #[expect(201, "application/json")]
             fn a(&self);
@@ -242,7 +610,7 @@ mod tests {
         assert_eq!("POST", &req.method);
         assert_eq!("api/user", &req.path);
         assert_eq!(
-            quote!(StatusCode::from_u16(201u16).unwrap()).to_string(),
+            quote!(StatusSet::new(vec![201u16..=201u16])).to_string(),
             expect.status.to_string()
         );
         assert_eq!(
@@ -250,9 +618,279 @@ mod tests {
             req.content_type.unwrap().to_string()
         );
         assert_eq!(
-            quote!("application/json".parse().unwrap()).to_string(),
-            expect.content_type.unwrap().to_string()
+            vec![quote!("application/json".parse().unwrap()).to_string()],
+            expect
+                .content_types
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn content_types() {
+        let Attr { expect, .. } = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[expect(200, mime::APPLICATION_JSON, "application/msgpack")]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert_eq!(
+            vec![
+                quote!(mime::APPLICATION_JSON).to_string(),
+                quote!("application/msgpack".parse().unwrap()).to_string()
+            ],
+            expect
+                .content_types
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>()
+        );
+    }
+
+    fn expect_status(attr: syn::Attribute) -> String {
+        let Attr { expect, .. } = Attr::from_raw(&syn::TraitItemMethod {
+            attrs: vec![parse_quote!(#[get]), attr],
+            ..parse_quote!(
+                fn a(&self);
+            )
+        })
+        .unwrap();
+        expect.status.to_string()
+    }
+
+    #[test]
+    fn status_set() {
+        assert_eq!(
+            quote!(StatusSet::new(vec![200u16..=200u16, 204u16..=204u16])).to_string(),
+            expect_status(parse_quote!(#[expect(200 | 204)]))
+        );
+        assert_eq!(
+            quote!(StatusSet::new(vec![200u16..=299u16])).to_string(),
+            expect_status(parse_quote!(#[expect(2xx, mime::APPLICATION_JSON)]))
+        );
+        assert_eq!(
+            quote!(StatusSet::new(vec![200u16..=299u16])).to_string(),
+            expect_status(parse_quote!(#[expect(success)]))
+        );
+        assert_eq!(
+            quote!(StatusSet::new(vec![200u16..=204u16, 300u16..=303u16])).to_string(),
+            expect_status(parse_quote!(#[expect((200..=204) | (300..304))]))
+        );
+        assert_eq!(
+            quote!(StatusSet::new(vec![
+                {
+                    let _code = StatusCode::CREATED.as_u16();
+                    _code..=_code
+                },
+                404u16..=404u16
+            ]))
+            .to_string(),
+            expect_status(parse_quote!(#[expect(StatusCode::CREATED | 404)]))
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_status_class() {
+        expect_status(parse_quote!(#[expect(6xx)]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_status_range() {
+        expect_status(parse_quote!(#[expect(300..=200)]));
+    }
+
+    #[test]
+    fn retry() {
+        let attr = Attr::from_raw(&parse_quote!(
+            #[post("api/user")]
+            #[retry]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert!(attr.retry);
+        let attr = Attr::from_raw(&parse_quote!(
+            #[post("api/user")]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert!(!attr.retry);
+    }
+
+    #[test]
+    fn timeout() {
+        let attr = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[timeout(ms = 500)]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert_eq!(
+            quote!(std::time::Duration::from_millis(500u64)).to_string(),
+            attr.timeout.unwrap().to_string()
+        );
+        let attr = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[timeout(secs = 5)]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert_eq!(
+            quote!(std::time::Duration::from_secs(5u64)).to_string(),
+            attr.timeout.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_timeout() {
+        let _ = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[timeout(minutes = 1)]
+            fn a(&self);
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn compress() {
+        let attr = Attr::from_raw(&parse_quote!(
+            #[post("api/user")]
+            #[compress(gzip)]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert_eq!(
+            quote!(interfacer_http::ContentEncoding::Gzip).to_string(),
+            attr.compress.unwrap().to_string()
+        );
+        let attr = Attr::from_raw(&parse_quote!(
+            #[post("api/user")]
+            #[compress(br)]
+            fn a(&self);
+        ))
+        .unwrap();
+        assert_eq!(
+            quote!(interfacer_http::ContentEncoding::Brotli).to_string(),
+            attr.compress.unwrap().to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_compress() {
+        let _ = Attr::from_raw(&parse_quote!(
+            #[post("api/user")]
+            #[compress(zstd)]
+            fn a(&self);
+        ))
+        .unwrap();
+    }
+
+    #[test]
+    fn error_body() {
+        let attr = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[error_body(ApiError, mime::APPLICATION_JSON)]
+            fn a(&self);
+        ))
+        .unwrap();
+        let error_body = attr.error_body.unwrap();
+        assert_eq!(quote!(ApiError).to_string(), error_body.typ.to_string());
+        assert_eq!(
+            quote!(mime::APPLICATION_JSON).to_string(),
+            error_body.content_type.to_string()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn error_body_without_content_type() {
+        let _ = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[error_body(ApiError)]
+            fn a(&self);
+        ))
+        .unwrap();
+    }
+
+    fn auth(attr: syn::Attribute) -> Option<(String, String)> {
+        let mut method: syn::TraitItemMethod = parse_quote!(
+            #[get]
+            fn a(&self);
         );
+        method.attrs.push(attr);
+        match Attr::from_raw(&method).unwrap().auth.unwrap() {
+            Auth::None => None,
+            Auth::Scheme { scheme, header } => Some((scheme.to_string(), header.to_string())),
+        }
+    }
+
+    #[test]
+    fn auth_schemes() {
+        let authorization = quote!(interfacer_http::http::header::AUTHORIZATION).to_string();
+        assert_eq!(
+            Some((
+                quote!(interfacer_http::AuthScheme::Bearer).to_string(),
+                authorization.clone()
+            )),
+            auth(parse_quote!(#[auth(bearer)]))
+        );
+        assert_eq!(
+            Some((
+                quote!(interfacer_http::AuthScheme::Basic).to_string(),
+                quote!("Proxy-Authorization").to_string()
+            )),
+            auth(parse_quote!(#[auth(basic, header = "Proxy-Authorization")]))
+        );
+        assert_eq!(
+            Some((
+                quote!(interfacer_http::AuthScheme::ApiKey).to_string(),
+                quote!("X-Api-Key").to_string()
+            )),
+            auth(parse_quote!(#[auth(api_key)]))
+        );
+        assert_eq!(None, auth(parse_quote!(#[auth(none)])));
+    }
+
+    #[test]
+    fn service_auth() {
+        let service = ServiceAttr::from_raw(&parse_quote!(
+            #[auth(api_key, header = "X-Token")]
+            trait Service {}
+        ))
+        .unwrap();
+        match service.auth.unwrap() {
+            Auth::Scheme { header, .. } => {
+                assert_eq!(quote!("X-Token").to_string(), header.to_string())
+            }
+            Auth::None => panic!("auth should not be none"),
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_auth_scheme() {
+        auth(parse_quote!(#[auth(digest)]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn invalid_auth_header() {
+        auth(parse_quote!(#[auth(api_key, header = "X Api Key")]));
+    }
+
+    #[test]
+    #[should_panic]
+    fn retry_with_args() {
+        let _ = Attr::from_raw(&parse_quote!(
+            #[get]
+            #[retry(3)]
+            fn a(&self);
+        ))
+        .unwrap();
     }
 
     #[test]
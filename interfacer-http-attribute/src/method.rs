@@ -1,9 +1,9 @@
 use proc_macro2::TokenStream;
 use quote::quote;
-use syn::{Block, TraitItemMethod};
+use syn::{Block, GenericArgument, PathArguments, PathSegment, ReturnType, TraitItemMethod, Type};
 
-use crate::attr::{Attr, Expect};
-use crate::param::Parameters;
+use crate::attr::{Attr, Auth, ErrorBody, Expect, ServiceAttr};
+use crate::param::{is_stream_body, HeaderKind, Parameters};
 use crate::parse::try_parse;
 use format_uri::gen_uri_format_expr;
 use proc_macro::Diagnostic;
@@ -12,23 +12,102 @@ use std::convert::TryInto;
 struct Context {
     attr: Attr,
     params: Parameters,
+    return_kind: ReturnKind,
+    stream_response: bool,
+}
+
+/// shape of `R` in return type `Result<R, E>`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+enum ReturnKind {
+    /// `Response<T>`
+    Response,
+    /// `T`
+    Body,
+    /// `(StatusCode, T)`
+    StatusBody,
+    /// `http::response::Parts`
+    Parts,
 }
 
 impl Context {
-    fn parse(raw_method: &TraitItemMethod) -> Result<Self, Diagnostic> {
-        let attr = Attr::from_raw(raw_method)?;
+    fn parse(raw_method: &TraitItemMethod, service: &ServiceAttr) -> Result<Self, Diagnostic> {
+        let mut attr = Attr::from_raw(raw_method)?;
+        if attr.error_body.is_none() {
+            attr.error_body = service.error_body.clone();
+        }
+        if attr.auth.is_none() {
+            attr.auth = service.auth.clone();
+        }
         let params = raw_method.clone().sig.inputs.try_into()?;
-        Ok(Self { attr, params })
+        let (return_kind, body_type) = return_type(&raw_method.sig.output);
+        let stream_response = match body_type {
+            Some(ty) => is_stream_body(ty),
+            None => false,
+        };
+        Ok(Self {
+            attr,
+            params,
+            return_kind,
+            stream_response,
+        })
+    }
+
+    /// whether the request should be sent by `HttpClient::request_stream`.
+    fn stream(&self) -> bool {
+        self.params.stream_body || self.stream_response
+    }
+}
+
+/// detect shape of `R` from return type `Result<R, E>`, and extract the body type.
+fn return_type(output: &ReturnType) -> (ReturnKind, Option<&Type>) {
+    fn last_segment<'a>(ty: &'a Type, name: &str) -> Option<&'a PathSegment> {
+        match ty {
+            Type::Path(path) => path.path.segments.last().filter(|seg| seg.ident == name),
+            _ => None,
+        }
+    }
+
+    fn first_type_arg<'a>(ty: &'a Type, name: &str) -> Option<&'a Type> {
+        match &last_segment(ty, name)?.arguments {
+            PathArguments::AngleBracketed(args) => match args.args.first()? {
+                GenericArgument::Type(ty) => Some(ty),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    let ret = match output {
+        ReturnType::Type(_, ty) => match first_type_arg(ty, "Result") {
+            Some(ret) => ret,
+            None => return (ReturnKind::Response, None),
+        },
+        ReturnType::Default => return (ReturnKind::Response, None),
+    };
+    if let Some(body) = first_type_arg(ret, "Response") {
+        return (ReturnKind::Response, Some(body));
+    }
+    if last_segment(ret, "Parts").is_some() {
+        return (ReturnKind::Parts, None);
+    }
+    if let Type::Tuple(tuple) = ret {
+        let elems = tuple.elems.iter().collect::<Vec<_>>();
+        if let [status, body] = elems.as_slice() {
+            if last_segment(status, "StatusCode").is_some() {
+                return (ReturnKind::StatusBody, Some(body));
+            }
+        }
     }
+    (ReturnKind::Body, Some(ret))
 }
 
-pub fn gen_block(method: &TraitItemMethod) -> Result<Block, Diagnostic> {
-    let context = Context::parse(method)?;
+pub fn gen_block(method: &TraitItemMethod, service: &ServiceAttr) -> Result<Block, Diagnostic> {
+    let context = Context::parse(method, service)?;
     let import_stmt = import();
     let define_content_type_stmt = define_content_type(&context.attr);
-    let send_request_stmt = send_request(build_request(&context)?);
-    let check_response_stmt = check_response(&context.attr.expect);
-    let return_stmt = return_response(&context.attr.expect);
+    let send_request_stmt = send_request(&context, build_request(&context)?);
+    let check_response_stmt = check_response(&context);
+    let return_stmt = return_response(&context);
     try_parse(quote!({
         #import_stmt
         #define_content_type_stmt
@@ -49,21 +128,28 @@ fn import() -> TokenStream {
         #[allow(unused_imports)]
         use interfacer_http::{
             mime::Mime,
-            http::{StatusCode, header::CONTENT_TYPE, Response},
-            ContentInto, ToContent, Unexpected,
+            http::{
+                StatusCode,
+                header::{ACCEPT, CONTENT_TYPE},
+                Response,
+            },
+            Body, ContentInto, MimeExt, RequestOptions, StatusSet, ToContent, Unexpected,
+            encode_path_segment, encode_query_component, format_accept,
         };
     )
 }
 
-fn define_content_type(Attr { req, expect }: &Attr) -> TokenStream {
-    use_idents!(_req_content_type, _expect_content_type);
+fn define_content_type(Attr { req, expect, .. }: &Attr) -> TokenStream {
+    use_idents!(_req_content_type, _expect_content_types);
     let define_req_content_type = match &req.content_type {
         Some(content_type) => quote!(let #_req_content_type: Mime = #content_type;),
         None => quote!(),
     };
-    let define_expect_content_type = match &expect.content_type {
-        Some(content_type) => quote!(let #_expect_content_type: Mime = #content_type;),
-        None => quote!(),
+    let content_types = &expect.content_types;
+    let define_expect_content_type = if content_types.is_empty() {
+        quote!()
+    } else {
+        quote!(let #_expect_content_types: Vec<Mime> = vec![#(#content_types),*];)
     };
     quote!(
         #define_req_content_type
@@ -71,88 +157,282 @@ fn define_content_type(Attr { req, expect }: &Attr) -> TokenStream {
     )
 }
 
-fn send_request(request: TokenStream) -> TokenStream {
+fn send_request(context: &Context, request: TokenStream) -> TokenStream {
     use_idents!(_resp);
+    let resp = match (context.stream(), context.stream_response) {
+        (false, _) => quote!(self.request(#request).await),
+        (true, false) => quote!({
+            let (_parts, _body) = self.request_stream(#request).await?.into_parts();
+            Ok::<_, <Self as interfacer_http::HttpClient>::Err>(Response::from_parts(
+                _parts,
+                _body.into_vec().await?,
+            ))
+        }),
+        (true, true) => quote!(self.request_stream(#request).await),
+    };
+    let timeout = match &context.attr.timeout {
+        Some(duration) => quote!(Some(#duration)),
+        None => quote!(self.helper().timeout()),
+    };
+    // streaming response body is not bounded by timeout.
     quote!(
-        let #_resp = self.request(#request).await?;
+        let #_resp = interfacer_http::timeout(#timeout, async { #resp }).await?;
     )
 }
 
-fn check_response(
-    Expect {
+fn check_response(context: &Context) -> TokenStream {
+    use_idents!(
+        _resp,
+        _expect_content_type,
+        _expect_content_types,
+        _expect_status
+    );
+    let Expect {
         status,
-        content_type,
-    }: &Expect,
-) -> TokenStream {
-    use_idents!(_resp, _expect_content_type);
-    let check_content_type = match content_type {
-        Some(_) => quote!(
-        match #_resp.headers().get(CONTENT_TYPE) {
-            None => return Err(Unexpected::new((CONTENT_TYPE, "Content-Type not found".to_owned()).into(), #_resp).into()),
-            Some(content_type) if !self.helper().match_mime(&#_expect_content_type, content_type) =>
-                return Err(Unexpected::new((CONTENT_TYPE, String::new()).into(), #_resp).into()),
-            _ => (),
-        }),
-        None => quote!(),
+        content_types,
+    } = &context.attr.expect;
+    let stream_response = context.stream_response;
+    // `Unexpected` holds a buffered response.
+    let unexpected_resp = if stream_response {
+        quote!({
+            let (_parts, _body) = #_resp.into_parts();
+            Response::from_parts(_parts, _body.into_vec().await?)
+        })
+    } else {
+        quote!(#_resp)
+    };
+    // decode body as the matched content type, with parameters like charset of the actual one.
+    let check_content_type = if content_types.is_empty() {
+        quote!()
+    } else {
+        quote!(
+        let #_expect_content_type: Mime = match #_resp.headers().get(CONTENT_TYPE) {
+            None => return Err(Unexpected::new((CONTENT_TYPE, "Content-Type not found".to_owned()).into(), #unexpected_resp).into()),
+            Some(content_type) => match #_expect_content_types
+                .iter()
+                .find(|expect| self.helper().match_mime(expect, content_type))
+            {
+                None => return Err(Unexpected::new((CONTENT_TYPE, String::new()).into(), #unexpected_resp).into()),
+                Some(expect) => match content_type.to_str().ok().and_then(|value| value.parse::<Mime>().ok()) {
+                    Some(actual) => expect.with_params_of(&actual),
+                    None => expect.clone(),
+                },
+            },
+        };)
+    };
+    let unexpected_status = match &context.attr.error_body {
+        Some(error_body) => unexpected_with_body(error_body, unexpected_resp),
+        None => quote!(Unexpected::new(#_expect_status.into(), #unexpected_resp)),
     };
     quote!(
-        if #status != #_resp.status() {
-            return Err(Unexpected::new(#status.into(), #_resp).into());
+        let #_expect_status = #status;
+        if !#_expect_status.contains(#_resp.status()) {
+            return Err(#unexpected_status.into());
         }
         #check_content_type
     )
 }
 
-fn return_response(expect: &Expect) -> TokenStream {
-    use_idents!(_resp, _expect_content_type);
-    let resp = match expect.content_type {
-        Some(_) => quote!({
-            let (_parts, _body) = #_resp.into_parts();
-            Response::from_parts(
-                _parts,
-                _body.content_into(&#_expect_content_type)?,
-            )
-        }),
-        None => quote!(Response::from_parts(#_resp.into_parts().0,())),
+/// decode body of unexpected response as error body, keep the decode error if fail.
+///
+/// parameters like charset of the actual content type are used if it matches the declared one.
+fn unexpected_with_body(
+    ErrorBody { typ, content_type }: &ErrorBody,
+    unexpected_resp: TokenStream,
+) -> TokenStream {
+    use_idents!(_expect_status, _unexpected_resp, _error_content_type);
+    quote!({
+        let #_unexpected_resp = #unexpected_resp;
+        let #_error_content_type: Mime = #content_type;
+        let #_error_content_type: Mime = match #_unexpected_resp.headers().get(CONTENT_TYPE) {
+            Some(content_type) if self.helper().match_mime(&#_error_content_type, content_type) => {
+                match content_type.to_str().ok().and_then(|value| value.parse::<Mime>().ok()) {
+                    Some(actual) => #_error_content_type.with_params_of(&actual),
+                    None => #_error_content_type,
+                }
+            }
+            _ => #_error_content_type,
+        };
+        Unexpected::decode::<#typ>(#_expect_status.into(), #_unexpected_resp, &#_error_content_type)
+    })
+}
+
+fn return_response(context: &Context) -> TokenStream {
+    use_idents!(_resp, _parts, _body, _expect_content_type);
+    let body = if context.stream_response {
+        quote!(#_body)
+    } else if context.attr.expect.content_types.is_empty() {
+        quote!(())
+    } else {
+        quote!(#_body.content_into(&#_expect_content_type)?)
+    };
+    let ret = match context.return_kind {
+        ReturnKind::Response => quote!(Response::from_parts(#_parts, #body)),
+        ReturnKind::Body => body,
+        ReturnKind::StatusBody => quote!((#_parts.status, #body)),
+        ReturnKind::Parts => quote!(#_parts),
     };
     quote!(
-        Ok(#resp)
+        let (#_parts, #_body) = #_resp.into_parts();
+        Ok(#ret)
     )
 }
 
-// TODO: using generic Body type
-fn build_request(Context { attr, params }: &Context) -> Result<TokenStream, Diagnostic> {
-    use_idents!(_req_content_type);
+fn build_request(context: &Context) -> Result<TokenStream, Diagnostic> {
+    use_idents!(_builder, _req_content_type, _authorization);
+    let Context { attr, params, .. } = context;
     let method = attr.req.method.as_str();
-    let mut headers = gen_headers(params);
-    match attr.req.content_type {
-        Some(_) => headers.push(quote!(header(CONTENT_TYPE, #_req_content_type.as_ref()))),
-        None => (),
+    let mut headers = gen_headers(params, !attr.expect.content_types.is_empty());
+    // bodies like multipart append parameters to `Content-Type`.
+    let define_req_content_type = match (params.body.as_ref(), &attr.req.content_type) {
+        (Some(body), Some(_)) if !params.stream_body => quote!(
+            let #_req_content_type: Mime = #body.to_content_type(&#_req_content_type);
+        ),
+        _ => quote!(),
     };
+    if attr.req.content_type.is_some() {
+        headers.push(quote!(#_builder.header(CONTENT_TYPE, #_req_content_type.as_ref());))
+    }
     let body = match (params.body.as_ref(), &attr.req.content_type) {
+        (Some(body), _) if params.stream_body => quote!(#body),
         (Some(body), Some(_)) => quote!(#body.to_content(&#_req_content_type)?),
         _ => quote!(Vec::new()),
     };
+    let body = if context.stream() && !params.stream_body {
+        quote!(Body::from(#body))
+    } else {
+        body
+    };
+    // credential is resolved for each request, replacing default or parameter header.
+    if let Some(Auth::Scheme { scheme, header }) = &attr.auth {
+        headers.push(quote!(
+            let #_authorization = self.helper().authorize(#scheme).await?;
+            if let Some(_headers) = #_builder.headers_mut() {
+                _headers.insert(#header, #_authorization);
+            }
+        ))
+    }
     let uri_format_expr = gen_uri_format_expr(&attr.req.path, params)?;
-    Ok(quote!(
-        self
-            .helper()
-            .request()
-            .uri(self.helper().parse_uri(&#uri_format_expr)?.as_str())
-            #(.#headers)*
-            .method(#method)
-            .body(#body)?
-    ))
+    let append_queries = gen_queries(params);
+    let retry = attr.retry;
+    let compress = match &attr.compress {
+        Some(encoding) => quote!(Some(#encoding)),
+        None => quote!(None),
+    };
+    Ok(quote!({
+        #define_req_content_type
+        let mut #_builder = self.helper().request();
+        #_builder.uri({
+            let mut _uri = self.helper().parse_uri(&#uri_format_expr)?;
+            #(#append_queries)*
+            _uri
+        }.as_str());
+        #(#headers)*
+        #_builder.extension(
+            RequestOptions::new()
+                .with_retry(#retry)
+                .with_compress(#compress),
+        );
+        #_builder.method(#method).body(#body)?
+    }))
 }
 
-fn gen_headers(params: &Parameters) -> Vec<TokenStream> {
-    params
+/// headers from parameters replace default headers of the same name,
+/// so does the negotiated `Accept` unless it's in parameters.
+fn gen_headers(params: &Parameters, negotiate: bool) -> Vec<TokenStream> {
+    use_idents!(_builder, _headers, _value, _expect_content_types);
+    if params.headers.is_empty() && !negotiate {
+        return Vec::new();
+    }
+    let mut set_headers = params
         .headers
         .iter()
-        .map(|(key, value)| quote!(header(#key, #value)))
+        .map(|(key, value, kind)| match kind {
+            HeaderKind::Single => quote!(#_headers.header(#key, #value);),
+            HeaderKind::Optional => quote!(
+                if let Some(#_value) = #value {
+                    #_headers.header(#key, #_value);
+                }
+            ),
+            HeaderKind::Multiple => quote!(
+                for #_value in #value {
+                    #_headers.header(#key, #_value);
+                }
+            ),
+            HeaderKind::MultipleRef => quote!(
+                for #_value in #value.iter().cloned() {
+                    #_headers.header(#key, #_value);
+                }
+            ),
+        })
+        .collect::<Vec<_>>();
+    if negotiate {
+        set_headers.push(quote!(
+            if !#_headers.headers_ref().map_or(false, |headers| headers.contains_key(ACCEPT)) {
+                #_headers.header(ACCEPT, format_accept(&#_expect_content_types).as_str());
+            }
+        ));
+    }
+    vec![quote!({
+        let mut #_headers = interfacer_http::http::request::Builder::new();
+        #(#set_headers)*
+        interfacer_http::replace_headers(&mut #_builder, #_headers.body(())?.into_parts().0.headers);
+    })]
+}
+
+fn gen_queries(params: &Parameters) -> Vec<TokenStream> {
+    params
+        .queries
+        .iter()
+        .map(|(key, value)| quote!(interfacer_http::append_query(&mut _uri, #key, &#value)?;))
         .collect()
 }
 
+#[cfg(test)]
+mod tests {
+    use super::{return_type, ReturnKind};
+    use quote::quote;
+    use syn::{parse_quote, ReturnType};
+
+    #[test]
+    fn test_return_type() {
+        fn kind_and_body(output: ReturnType) -> (ReturnKind, Option<String>) {
+            let (kind, body) = return_type(&output);
+            (kind, body.map(|ty| quote!(#ty).to_string()))
+        }
+        assert_eq!(
+            (ReturnKind::Response, Some(quote!(User).to_string())),
+            kind_and_body(parse_quote!(-> Result<Response<User>, Self::Error>))
+        );
+        assert_eq!(
+            (
+                ReturnKind::Response,
+                Some(quote!(interfacer_http::Body).to_string())
+            ),
+            kind_and_body(parse_quote!(
+                -> core::result::Result<http::Response<interfacer_http::Body>, Self::Error>
+            ))
+        );
+        assert_eq!(
+            (ReturnKind::Body, Some(quote!(User).to_string())),
+            kind_and_body(parse_quote!(-> Result<User, Self::Error>))
+        );
+        assert_eq!(
+            (ReturnKind::StatusBody, Some(quote!(Vec<User>).to_string())),
+            kind_and_body(parse_quote!(-> Result<(http::StatusCode, Vec<User>), Self::Error>))
+        );
+        assert_eq!(
+            (ReturnKind::Body, Some(quote!((u8, User)).to_string())),
+            kind_and_body(parse_quote!(-> Result<(u8, User), Self::Error>))
+        );
+        assert_eq!(
+            (ReturnKind::Parts, None),
+            kind_and_body(parse_quote!(-> Result<http::response::Parts, Self::Error>))
+        );
+        assert_eq!((ReturnKind::Response, None), kind_and_body(parse_quote!()));
+    }
+}
+
 mod format_uri {
     use super::Parameters;
     use crate::parse::try_parse;
@@ -163,9 +443,16 @@ mod format_uri {
     use regex::Regex;
     use syn::{parse_quote, punctuated::Punctuated, Expr, Macro, Token};
 
-    const DYN_URI_PATTERN: &str = r#"(?P<value>\{\w+})"#;
+    const DYN_URI_PATTERN: &str = r#"(?P<value>\{(?P<raw>\+)?(?P<name>\w+)})"#;
     const VAL_NAME: &str = "value";
+    const RAW_NAME: &str = "raw";
+    const PARAM_NAME: &str = "name";
+    const QUERY_DELIMITER: char = '?';
 
+    /// generate `format!` expression for uri template.
+    ///
+    /// `{name}` is percent-encoded as a path segment, or as a component of query after `?`;
+    /// `{+name}` is spliced verbatim, for values that are already encoded.
     pub fn gen_uri_format_expr(raw_uri: &str, params: &Parameters) -> Result<Macro, Diagnostic> {
         lazy_static! {
             static ref URI_REGEX: Regex = Regex::new(DYN_URI_PATTERN).unwrap();
@@ -174,21 +461,27 @@ mod format_uri {
         let mut format_expr = try_parse::<Macro>(quote!(format!()))?;
         let mut values = Vec::new();
         let mut param_list = Punctuated::<Expr, Token![,]>::new();
+        let query_start = raw_uri.find(QUERY_DELIMITER).unwrap_or(raw_uri.len());
         for capture in URI_REGEX.captures_iter(raw_uri) {
-            let pattern: &str = &capture[VAL_NAME];
-            match params.values.get(&Ident::new(
-                pattern.trim_start_matches('{').trim_end_matches('}'),
-                Span::call_site(),
-            )) {
-                Some(ident) => values.push(ident),
+            let pattern = &capture[VAL_NAME];
+            let name = Ident::new(&capture[PARAM_NAME], Span::call_site());
+            let value = match params.values.get(&name) {
+                Some(ident) => ident,
                 None => {
                     return Err(Diagnostic::new(
                         Level::Error,
                         format!("uri template variable {} has no parameter support", pattern),
                     ));
                 }
+            };
+            if capture.name(RAW_NAME).is_some() {
+                values.push(quote!(#value));
+            } else if capture.get(0).unwrap().start() < query_start {
+                values.push(quote!(encode_path_segment(&#value)?));
+            } else {
+                values.push(quote!(encode_query_component(&#value)));
             }
-            uri_template = uri_template.replace(pattern, "{}");
+            uri_template = uri_template.replacen(pattern, "{}", 1);
         }
         param_list.push(parse_quote!(#uri_template));
         for value in values {
@@ -225,6 +518,7 @@ mod format_uri {
             dyn_uri_match("/api/user/{id}", &["{id}"][..]);
             dyn_uri_match("/api/user/{id}/name", &["{id}"][..]);
             dyn_uri_match("/api/user-{id}/name", &["{id}"][..]);
+            dyn_uri_match("/api/file/{+path}?v={+v}", &["{+path}", "{+v}"][..]);
         }
 
         fn assert_gen_uri_format_expr(uri: &str, values: &[&str], expect_token: TokenStream) {
@@ -234,7 +528,9 @@ mod format_uri {
                     .map(|value| Ident::new(*value, Span::call_site()))
                     .collect(),
                 headers: Vec::new(),
+                queries: Vec::new(),
                 body: None,
+                stream_body: false,
             };
             let token = gen_uri_format_expr(uri, &parameters).unwrap();
             assert_eq!(expect_token.to_string(), quote!(#token).to_string());
@@ -245,12 +541,16 @@ mod format_uri {
             assert_gen_uri_format_expr(
                 "/api/user/{id}?age={age}",
                 &["id", "age"][..],
-                quote!(format!("/api/user/{}?age={}", id, age)),
+                quote!(format!(
+                    "/api/user/{}?age={}",
+                    encode_path_segment(&id)?,
+                    encode_query_component(&age)
+                )),
             );
             assert_gen_uri_format_expr(
                 "/api/user/{id}",
                 &["id", "age"][..],
-                quote!(format!("/api/user/{}", id)),
+                quote!(format!("/api/user/{}", encode_path_segment(&id)?)),
             );
             assert_gen_uri_format_expr(
                 "/api/user",
@@ -260,7 +560,21 @@ mod format_uri {
             assert_gen_uri_format_expr(
                 "/api/user-{id}",
                 &["id", "age"][..],
-                quote!(format!("/api/user-{}", id)),
+                quote!(format!("/api/user-{}", encode_path_segment(&id)?)),
+            );
+        }
+
+        #[test]
+        fn test_gen_uri_format_expr_raw() {
+            assert_gen_uri_format_expr(
+                "/api/file/{+path}?age={+age}",
+                &["path", "age"][..],
+                quote!(format!("/api/file/{}?age={}", path, age)),
+            );
+            assert_gen_uri_format_expr(
+                "/api/{+path}/{id}",
+                &["path", "id"][..],
+                quote!(format!("/api/{}/{}", path, encode_path_segment(&id)?)),
             );
         }
 
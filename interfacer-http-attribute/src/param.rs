@@ -4,21 +4,101 @@ use proc_macro2::{Ident, TokenStream};
 use quote::quote;
 use std::collections::HashSet;
 use std::convert::{TryFrom, TryInto};
-use syn::{punctuated::Punctuated, FnArg, Lit, Meta, NestedMeta, Pat, Token};
+use syn::{punctuated::Punctuated, FnArg, Lit, Meta, NestedMeta, Pat, Token, Type, TypeParamBound};
 
 const HEADER: &str = "header";
 const BODY: &str = "body";
+const QUERY: &str = "query";
+
+const OPTION: &str = "Option";
+const COLLECTIONS: [&str; 5] = ["Vec", "VecDeque", "LinkedList", "HashSet", "BTreeSet"];
+const ITERATORS: [&str; 2] = ["IntoIterator", "Iterator"];
+const STREAM_BODY: &str = "Body";
+
+/// whether `ty` is the streaming `interfacer_http::Body`.
+pub fn is_stream_body(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => match path.path.segments.last() {
+            Some(seg) => seg.ident == STREAM_BODY && seg.arguments.is_empty(),
+            None => false,
+        },
+        Type::Paren(paren) => is_stream_body(&paren.elem),
+        _ => false,
+    }
+}
+
+/// How many times a header parameter is set, deduced from its type.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum HeaderKind {
+    /// `T`: set once.
+    Single,
+    /// `Option<T>`: set only when `Some`.
+    Optional,
+    /// `Vec<T>`, `[T; N]` or `impl IntoIterator<Item = T>`: set once for each item.
+    Multiple,
+    /// `&[T]` or `&Vec<T>`: set once for each cloned item.
+    MultipleRef,
+}
+
+impl From<&Type> for HeaderKind {
+    fn from(ty: &Type) -> Self {
+        fn is_collection(ty: &Type) -> bool {
+            match ty {
+                Type::Path(path) => match path.path.segments.last() {
+                    Some(seg) => COLLECTIONS.contains(&seg.ident.to_string().as_str()),
+                    None => false,
+                },
+                Type::Array(_) => true,
+                Type::Paren(paren) => is_collection(&paren.elem),
+                _ => false,
+            }
+        }
+
+        match ty {
+            Type::Path(path) => match path.path.segments.last() {
+                Some(seg) if seg.ident == OPTION => HeaderKind::Optional,
+                _ if is_collection(ty) => HeaderKind::Multiple,
+                _ => HeaderKind::Single,
+            },
+            Type::Array(_) => HeaderKind::Multiple,
+            Type::Reference(reference) => match reference.elem.as_ref() {
+                Type::Slice(_) => HeaderKind::MultipleRef,
+                elem if is_collection(elem) => HeaderKind::MultipleRef,
+                _ => HeaderKind::Single,
+            },
+            Type::ImplTrait(impl_trait) => {
+                let is_iterator = impl_trait.bounds.iter().any(|bound| match bound {
+                    TypeParamBound::Trait(bound) => match bound.path.segments.last() {
+                        Some(seg) => ITERATORS.contains(&seg.ident.to_string().as_str()),
+                        None => false,
+                    },
+                    _ => false,
+                });
+                if is_iterator {
+                    HeaderKind::Multiple
+                } else {
+                    HeaderKind::Single
+                }
+            }
+            Type::Paren(paren) => paren.elem.as_ref().into(),
+            _ => HeaderKind::Single,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub struct Parameters {
     pub values: HashSet<Ident>,
-    pub headers: Vec<(TokenStream, Ident)>,
+    pub headers: Vec<(TokenStream, Ident, HeaderKind)>,
+    pub queries: Vec<(TokenStream, Ident)>,
     pub body: Option<Ident>,
+    pub stream_body: bool,
 }
 
 #[derive(Debug)]
 enum Parameter {
     Header(TokenStream),
+    Query(Option<TokenStream>),
     Body,
 }
 
@@ -33,6 +113,16 @@ impl Parameter {
             )),
         }
     }
+
+    fn query(nested: Punctuated<NestedMeta, Token![,]>) -> Result<TokenStream, Diagnostic> {
+        match nested.first() {
+            Some(NestedMeta::Lit(Lit::Str(lit))) if nested.len() == 1 => Ok(quote!(#lit)),
+            _ => Err(Diagnostic::new(
+                Level::Error,
+                "query parameter name should be str literal",
+            )),
+        }
+    }
 }
 
 impl TryFrom<AttrMeta> for Parameter {
@@ -47,6 +137,12 @@ impl TryFrom<AttrMeta> for Parameter {
                 )),
             },
             BODY => Ok(Parameter::Body),
+            QUERY => match meta {
+                AttrMeta::Name(_) => Ok(Parameter::Query(None)),
+                AttrMeta::List { name: _, nested } => {
+                    Ok(Parameter::Query(Some(Self::query(nested)?)))
+                }
+            },
             _ => Err(Diagnostic::new(
                 Level::Error,
                 format!("unsupported attribute `{}`", meta.name()),
@@ -60,7 +156,9 @@ impl TryFrom<Punctuated<FnArg, Token![,]>> for Parameters {
     fn try_from(args: Punctuated<FnArg, Token![,]>) -> Result<Self, Self::Error> {
         let mut values = HashSet::new();
         let mut headers = Vec::new();
+        let mut queries = Vec::new();
         let mut body = None;
+        let mut stream_body = false;
         for arg in args.iter() {
             if let FnArg::Typed(pat) = arg {
                 if let Pat::Ident(name) = pat.pat.as_ref() {
@@ -80,16 +178,26 @@ impl TryFrom<Punctuated<FnArg, Token![,]>> for Parameters {
                             values.insert(name.ident.clone());
                         }
                         1 => match params.into_iter().nth(0).unwrap() {
-                            Parameter::Header(rename) => headers.push((rename, name.ident.clone())),
+                            Parameter::Header(rename) => {
+                                headers.push((rename, name.ident.clone(), pat.ty.as_ref().into()))
+                            }
+                            Parameter::Query(rename) => {
+                                let key = rename.unwrap_or_else(|| {
+                                    let key = name.ident.to_string();
+                                    quote!(#key)
+                                });
+                                queries.push((key, name.ident.clone()))
+                            }
                             Parameter::Body => {
                                 check_duplicate(&name.ident, &body)?;
                                 body = Some(name.ident.clone());
+                                stream_body = is_stream_body(&pat.ty);
                             }
                         },
                         _ => {
                             return Err(Diagnostic::new(
                                 Level::Error,
-                                "parameter can only be one of 'value', 'header' or 'body'",
+                                "parameter can only be one of 'value', 'header', 'query' or 'body'",
                             ));
                         }
                     }
@@ -99,7 +207,9 @@ impl TryFrom<Punctuated<FnArg, Token![,]>> for Parameters {
         Ok(Parameters {
             values,
             headers,
+            queries,
             body,
+            stream_body,
         })
     }
 }
@@ -168,6 +278,30 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn param_query() -> Result<(), Diagnostic> {
+        assert_eq!(
+            quote!("age-max").to_string(),
+            Parameter::query(Punctuated::from_iter(
+                vec![NestedMeta::Lit(Lit::Str(LitStr::new(
+                    "age-max",
+                    Span::call_site(),
+                )))]
+                .into_iter(),
+            ))?
+            .to_string()
+        );
+        assert_eq!(
+            "query parameter name should be str literal",
+            Parameter::query(Punctuated::new()).unwrap_err().message()
+        );
+        assert!(matches!(
+            AttrMeta::Name(Ident::new(QUERY, Span::call_site())).try_into()?,
+            Parameter::Query(None)
+        ));
+        Ok(())
+    }
+
     #[test]
     fn param_try_from() -> Result<(), Diagnostic> {
         assert!(matches!(
@@ -208,6 +342,39 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn header_kind() -> Result<(), Diagnostic> {
+        fn kind(ty: TokenStream) -> Result<HeaderKind, Diagnostic> {
+            Ok((&try_parse::<Type>(ty)?).into())
+        }
+        assert_eq!(HeaderKind::Single, kind(quote!(&str))?);
+        assert_eq!(HeaderKind::Single, kind(quote!(String))?);
+        assert_eq!(HeaderKind::Single, kind(quote!(http::HeaderValue))?);
+        assert_eq!(HeaderKind::Single, kind(quote!(impl Display))?);
+        assert_eq!(HeaderKind::Optional, kind(quote!(Option<&str>))?);
+        assert_eq!(
+            HeaderKind::Optional,
+            kind(quote!(std::option::Option<String>))?
+        );
+        assert_eq!(HeaderKind::Multiple, kind(quote!(Vec<String>))?);
+        assert_eq!(HeaderKind::Multiple, kind(quote!([&str; 2]))?);
+        assert_eq!(
+            HeaderKind::Multiple,
+            kind(quote!(impl IntoIterator<Item = &'static str>))?
+        );
+        assert_eq!(
+            HeaderKind::Multiple,
+            kind(quote!(impl Iterator<Item = String> + Send))?
+        );
+        assert_eq!(HeaderKind::MultipleRef, kind(quote!(&[&str]))?);
+        assert_eq!(HeaderKind::MultipleRef, kind(quote!(&Vec<String>))?);
+        assert_eq!(
+            HeaderKind::MultipleRef,
+            kind(quote!(&'a std::collections::HashSet<String>))?
+        );
+        Ok(())
+    }
+
     fn parse_params(params_token: TokenStream) -> Result<Parameters, Diagnostic> {
         let func: ItemFn = try_parse(quote!(fn foo(#params_token){}))?;
         func.sig.inputs.try_into()
@@ -218,7 +385,9 @@ mod tests {
         let params = parse_params(quote!())?;
         assert!(params.values.is_empty());
         assert!(params.headers.is_empty());
+        assert!(params.queries.is_empty());
         assert!(params.body.is_none());
+        assert!(!params.stream_body);
         Ok(())
     }
 
@@ -240,7 +409,11 @@ mod tests {
             #[body]
             body: String,
             #[header(COOKIE)]
-            cookie: &str
+            cookie: &str,
+            #[query]
+            age_max: Option<u8>,
+            #[query("tag")]
+            tags: &[&str]
         ))?;
         assert!(params
             .values
@@ -250,7 +423,27 @@ mod tests {
             &Ident::new("cookie", Span::call_site()),
             &params.headers[0].1
         );
+        assert_eq!(HeaderKind::Single, params.headers[0].2);
+        assert_eq!(
+            quote!("age_max").to_string(),
+            params.queries[0].0.to_string()
+        );
+        assert_eq!(
+            &Ident::new("age_max", Span::call_site()),
+            &params.queries[0].1
+        );
+        assert_eq!(quote!("tag").to_string(), params.queries[1].0.to_string());
+        assert_eq!(&Ident::new("tags", Span::call_site()), &params.queries[1].1);
         assert_eq!(Some(Ident::new("body", Span::call_site())), params.body);
+        assert!(!params.stream_body);
+        Ok(())
+    }
+
+    #[test]
+    fn params_try_from_stream_body() -> Result<(), Diagnostic> {
+        let params = parse_params(quote!(#[body] data: interfacer_http::Body))?;
+        assert_eq!(Some(Ident::new("data", Span::call_site())), params.body);
+        assert!(params.stream_body);
         Ok(())
     }
 }
@@ -1,3 +1,4 @@
+use crate::attr::ServiceAttr;
 use crate::method::gen_block;
 use proc_macro2::TokenStream;
 use quote::quote;
@@ -5,6 +6,10 @@ use syn::{parse_quote, ImplItem, ImplItemMethod, ItemImpl, ItemTrait, TraitItem,
 
 pub fn implement(item_trait: ItemTrait) -> TokenStream {
     let trait_name = item_trait.ident.clone();
+    let service_attr = ServiceAttr::from_raw(&item_trait).unwrap_or_else(|err| {
+        err.emit();
+        std::process::exit(1)
+    });
     let mut super_traits = item_trait.supertraits.clone();
     super_traits.push(parse_quote!(interfacer_http::HttpClient));
 
@@ -22,7 +27,7 @@ pub fn implement(item_trait: ItemTrait) -> TokenStream {
                 defaultness: None,
                 vis: Visibility::Inherited,
                 sig: method.sig.clone(),
-                block: gen_block(method).unwrap_or_else(|err| {
+                block: gen_block(method, &service_attr).unwrap_or_else(|err| {
                     err.emit();
                     std::process::exit(1)
                 }),
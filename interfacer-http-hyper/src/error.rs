@@ -1,5 +1,8 @@
 use derive_more::{Display, From};
-use interfacer_http::{http, url, FromContentError, ToContentError, Unexpected};
+use interfacer_http::{
+    http, url, AuthError, FromContentError, PathSegmentError, Timeout, ToContentError,
+    Unexpected,
+};
 
 pub type Result<T> = std::result::Result<T, Error>;
 
@@ -22,6 +25,18 @@ pub enum Error {
 
     #[display(fmt = "{}", _0)]
     Unexpected(Unexpected),
+
+    #[display(fmt = "{}", _0)]
+    PathSegmentError(PathSegmentError),
+
+    #[display(fmt = "io error: {}", _0)]
+    IoError(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    Timeout(Timeout),
+
+    #[display(fmt = "{}", _0)]
+    AuthError(AuthError),
 }
 
 impl std::error::Error for Error {}
@@ -1,10 +1,17 @@
 pub extern crate hyper;
 pub use error::{Error, Result};
+#[cfg(feature = "test-server")]
+pub use server::TestServer;
 
+use futures::TryStreamExt;
 use hyper::client::connect::{Connect, HttpConnector};
+use hyper::Chunk;
 use interfacer_http::http::{Request, Response};
-use interfacer_http::{async_trait, Helper, HttpClient};
+use interfacer_http::{async_trait, Body, Helper, HttpClient};
+use std::io;
 mod error;
+#[cfg(feature = "test-server")]
+mod server;
 
 #[derive(Clone)]
 pub struct Client<C> {
@@ -56,6 +63,19 @@ where
         Ok(Response::from_parts(parts, data))
     }
 
+    async fn request_stream(&self, req: Request<Body>) -> Result<Response<Body>> {
+        let (parts, body) = req.into_parts();
+        let (parts, body) = self
+            .inner
+            .request(Request::from_parts(parts, hyper::Body::wrap_stream(body)))
+            .await?
+            .into_parts();
+        let body = body
+            .map_ok(Chunk::into_bytes)
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err));
+        Ok(Response::from_parts(parts, Body::wrap_stream(body)))
+    }
+
     fn helper(&self) -> &Helper {
         &self.helper
     }
@@ -0,0 +1,215 @@
+use crate::{Client, Result};
+use futures::channel::oneshot;
+use futures::FutureExt;
+use hyper::client::connect::HttpConnector;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::Server;
+use interfacer_http::http::{
+    header::{HeaderValue, HOST},
+    Request, Response, StatusCode, Uri,
+};
+use interfacer_http::{mock, url::Url, Helper};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+/// A hyper server on a loopback address, for tests exercising the real network path.
+///
+/// Requests are buffered and passed to the handler in the same shape as `mock::Client`,
+/// their uri are absolute, so handlers and `mock::Router` of `mock::Client` can be reused.
+/// Errors of the handler are responded as `500 Internal Server Error`.
+///
+/// The server must be started in a tokio runtime, and is shut down on drop.
+///
+/// ```rust,ignore
+/// let server = TestServer::new(|_req| async { Ok(Response::new(b"hello".to_vec())) })?;
+/// let client = server.client();
+/// ```
+pub struct TestServer {
+    addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl TestServer {
+    /// bind `127.0.0.1:0` and serve in background.
+    pub fn new<H, F>(handler: H) -> Result<Self>
+    where
+        H: Fn(Request<Vec<u8>>) -> F + Send + Sync + 'static,
+        F: Future<Output = mock::Result<Response<Vec<u8>>>> + Send + 'static,
+    {
+        let handler = Arc::new(handler);
+        let server = Server::try_bind(&([127, 0, 0, 1], 0).into())?.serve(make_service_fn(
+            move |_| {
+                let handler = handler.clone();
+                async move {
+                    Ok::<_, hyper::Error>(service_fn(move |req| serve(handler.clone(), req)))
+                }
+            },
+        ));
+        let addr = server.local_addr();
+        let (shutdown, signal) = oneshot::channel::<()>();
+        hyper::rt::spawn(
+            server
+                .with_graceful_shutdown(signal.map(|_| ()))
+                .map(|_| ()),
+        );
+        Ok(Self {
+            addr,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// address the server listening on.
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// base url of the server.
+    pub fn base_url(&self) -> Url {
+        format!("http://{}", self.addr)
+            .parse()
+            .expect("invalid socket address")
+    }
+
+    /// a helper bound to the base url of the server.
+    pub fn helper(&self) -> Helper {
+        Helper::new().with_base_url(self.base_url())
+    }
+
+    /// a hyper client with the helper of the server.
+    pub fn client(&self) -> Client<HttpConnector> {
+        Client::new().with_helper(self.helper())
+    }
+}
+
+impl Drop for TestServer {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+async fn serve<H, F>(
+    handler: Arc<H>,
+    req: Request<hyper::Body>,
+) -> std::result::Result<Response<hyper::Body>, hyper::Error>
+where
+    H: Fn(Request<Vec<u8>>) -> F,
+    F: Future<Output = mock::Result<Response<Vec<u8>>>>,
+{
+    let (mut parts, mut body) = req.into_parts();
+    let mut data = Vec::new();
+    while let Some(chunk) = body.next().await {
+        data.extend_from_slice(&chunk?);
+    }
+    if parts.uri.host().is_none() {
+        if let Some(uri) = absolute_uri(&parts.uri, parts.headers.get(HOST)) {
+            parts.uri = uri;
+        }
+    }
+    let resp = match handler(Request::from_parts(parts, data)).await {
+        Ok(resp) => resp.map(hyper::Body::from),
+        Err(err) => {
+            let mut resp = Response::new(hyper::Body::from(err.to_string()));
+            *resp.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            resp
+        }
+    };
+    Ok(resp)
+}
+
+/// join the origin-form uri with the `Host` header.
+fn absolute_uri(uri: &Uri, host: Option<&HeaderValue>) -> Option<Uri> {
+    let host = host?.to_str().ok()?;
+    let path = uri.path_and_query().map_or("/", |path| path.as_str());
+    format!("http://{}{}", host, path).parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TestServer;
+    use crate::Error;
+    use futures::stream;
+    use hyper::Chunk;
+    use interfacer_http::http::{
+        header::{CONTENT_LENGTH, TRANSFER_ENCODING},
+        Method, Request, Response,
+    };
+    use interfacer_http::mock::{self, Params, Router};
+    use interfacer_http::{Body, HttpClient};
+    use std::io;
+
+    async fn echo(req: Request<Vec<u8>>) -> mock::Result<Response<Vec<u8>>> {
+        let chunked = req.headers().contains_key(TRANSFER_ENCODING);
+        Ok(Response::builder()
+            .status(200)
+            .header("X-Uri", req.uri().to_string().as_str())
+            .header("X-Chunked", if chunked { "true" } else { "false" })
+            .body(req.into_body())?)
+    }
+
+    #[tokio::test]
+    async fn round_trip() -> Result<(), Error> {
+        let server = TestServer::new(echo)?;
+        let client = server.client();
+        let url = server.base_url().join("/api/user?id=0")?;
+        let req = Request::post(url.as_str()).body(b"hexi".to_vec())?;
+        let resp = client.request(req).await?;
+        assert_eq!(200, resp.status());
+        assert_eq!(url.as_str(), resp.headers().get("X-Uri").unwrap());
+        assert_eq!("false", resp.headers().get("X-Chunked").unwrap());
+        assert_eq!("4", resp.headers().get(CONTENT_LENGTH).unwrap());
+        assert_eq!(b"hexi", resp.body().as_slice());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn chunked_body() -> Result<(), Error> {
+        let server = TestServer::new(echo)?;
+        let client = server.client();
+        let chunks = vec![b"hexi".to_vec(), b"lee".to_vec()]
+            .into_iter()
+            .map(|chunk| io::Result::Ok(Chunk::from(chunk).into_bytes()));
+        let body = Body::wrap_stream(stream::iter(chunks));
+        let req = Request::put(server.base_url().as_str()).body(body)?;
+        let resp = client.request_stream(req).await?;
+        assert_eq!("true", resp.headers().get("X-Chunked").unwrap());
+        assert_eq!(b"hexilee".to_vec(), resp.into_body().into_vec().await?);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn handler_error() -> Result<(), Error> {
+        let server = TestServer::new(|req: Request<Vec<u8>>| async move {
+            Ok(Response::builder().status(1000).body(req.into_body())?)
+        })?;
+        let client = server.client();
+        let resp = client
+            .request(Request::get(server.base_url().as_str()).body(Vec::new())?)
+            .await?;
+        assert_eq!(500, resp.status());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn router() -> Result<(), Error> {
+        let router = Router::new().with_route(Method::GET, "/api/user/{id}", |req| {
+            let id = Params::of(&req).get("id").unwrap().as_bytes().to_vec();
+            async { Ok(Response::new(id)) }
+        });
+        let server = TestServer::new(move |req| router.handle(req))?;
+        let client = server.client();
+        let url = server.base_url().join("/api/user/0")?;
+        let resp = client
+            .request(Request::get(url.as_str()).body(Vec::new())?)
+            .await?;
+        assert_eq!(b"0", resp.body().as_slice());
+        let url = server.base_url().join("/api/file/0")?;
+        let resp = client
+            .request(Request::get(url.as_str()).body(Vec::new())?)
+            .await?;
+        assert_eq!(404, resp.status());
+        Ok(())
+    }
+}
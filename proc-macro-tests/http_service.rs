@@ -11,14 +11,37 @@ macro_rules! format {
     }}
 }
 
+use futures::stream::{self, StreamExt};
+use futures_timer::Delay;
+#[cfg(feature = "compression")]
+use interfacer_http::middleware::Compression;
+#[cfg(feature = "cassette")]
+use interfacer_http::mock::{Cassette, Recorder, Replay};
 use interfacer_http::{
-    http::{header::CONTENT_TYPE, header::COOKIE, Request, Response},
-    http_service, mime,
-    mock::{Client, Error},
+    http::{
+        header::{
+            ACCEPT, ACCEPT_ENCODING, ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_ENCODING,
+            CONTENT_TYPE, COOKIE, SET_COOKIE, USER_AGENT,
+        },
+        response::Parts,
+        HeaderValue, Method, Request, Response, StatusCode, Version,
+    },
+    http_service,
+    middleware::{map_request, CookieStore, HttpClientExt, Retry},
+    mime,
+    mock::{
+        parse_multipart, Client, Error, Expectation, Fault, FaultInjector, Injection, MockServer,
+        Params, Router, Stub,
+    },
     url::Url,
-    ContentInto, ToContent,
+    AuthError, AuthScheme, Body, ContentInto, Credential, Helper, MimeMatch, Multipart,
+    RefreshingToken, ToContent, Token,
 };
 use serde_derive::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
 #[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
 struct User {
@@ -26,6 +49,12 @@ struct User {
     age: i32,
 }
 
+#[derive(Serialize)]
+struct Filter<'a> {
+    name: Option<&'a str>,
+    age_min: Option<u8>,
+}
+
 const MOCK_BASE_URL: &str = "https://mock.rs";
 const DEFAULT_COOKIE: &str = "cookie=cookie";
 
@@ -40,10 +69,55 @@ trait UserService {
     #[expect(200, mime::APPLICATION_JSON)]
     async fn get_user(&self, id: u64) -> Result<Response<User>, Self::Error>;
 
+    #[get("/api/user/{id}")]
+    #[expect(200, mime::APPLICATION_JSON)]
+    async fn get_user_body(&self, id: u64) -> Result<User, Self::Error>;
+
+    #[get("/api/user/{id}")]
+    #[expect(2xx, mime::APPLICATION_JSON)]
+    async fn get_user_with_status(&self, id: u64) -> Result<(StatusCode, User), Self::Error>;
+
+    #[get("/api/user/{id}")]
+    #[expect(200, mime::APPLICATION_MSGPACK, mime::APPLICATION_JSON)]
+    async fn negotiate_user(
+        &self,
+        id: u64,
+        #[header(ACCEPT)] accept: Option<&str>,
+    ) -> Result<Response<User>, Self::Error>;
+
+    #[get("/api/user/{id}")]
+    async fn get_user_parts(&self, id: u64) -> Result<Parts, Self::Error>;
+
+    #[get("/api/user/name/{name}")]
+    #[expect(200, mime::APPLICATION_JSON)]
+    async fn get_user_by_name(&self, name: &str) -> Result<Response<User>, Self::Error>;
+
+    #[get("/api/file/{+path}")]
+    async fn get_file(&self, path: &str) -> Result<Response<()>, Self::Error>;
+
     #[get("/api/user?age_max={age_max}")]
     #[expect(200, mime::APPLICATION_JSON)]
     async fn get_users(&self, age_max: u8) -> Result<Response<Vec<User>>, Self::Error>;
 
+    #[get("/api/me")]
+    #[expect(200, mime::APPLICATION_JSON)]
+    async fn get_me(
+        &self,
+        #[header(AUTHORIZATION)] token: Option<&str>,
+        #[header(ACCEPT_LANGUAGE)] languages: &[&str],
+        #[header("X-Trace")] traces: Vec<String>,
+    ) -> Result<Response<User>, Self::Error>;
+
+    #[get("/api/user?sort={sort}")]
+    #[expect(200, mime::APPLICATION_JSON)]
+    async fn search_users(
+        &self,
+        sort: &str,
+        #[query] age_max: Option<u8>,
+        #[query("tag")] tags: &[&str],
+        #[query] filter: &Filter<'_>,
+    ) -> Result<Response<Vec<User>>, Self::Error>;
+
     #[put("/api/user/{id}", mime::APPLICATION_JSON)]
     #[expect(200, "application/json")]
     async fn put_user(
@@ -53,6 +127,22 @@ trait UserService {
         #[header(COOKIE)] cookie: &str,
     ) -> Result<Response<User>, Self::Error>;
 
+    #[get("/api/file/{id}")]
+    #[expect(200, mime::APPLICATION_OCTET_STREAM)]
+    async fn download(&self, id: u64) -> Result<Response<Body>, Self::Error>;
+
+    #[get("/api/file/{id}")]
+    #[expect(200, mime::APPLICATION_OCTET_STREAM)]
+    async fn download_body(&self, id: u64) -> Result<Body, Self::Error>;
+
+    #[post("/api/avatar", mime::MULTIPART_FORM_DATA)]
+    #[expect(201)]
+    async fn upload_avatar(&self, #[body] form: &Multipart) -> Result<Response<()>, Self::Error>;
+
+    #[put("/api/file/{id}", mime::APPLICATION_OCTET_STREAM)]
+    #[expect(200, mime::APPLICATION_JSON)]
+    async fn upload(&self, id: u64, #[body] data: Body) -> Result<Response<u64>, Self::Error>;
+
     #[post("/api/user", mime::APPLICATION_WWW_FORM_URLENCODED)]
     #[expect(201, mime::APPLICATION_MSGPACK)]
     async fn post_user(
@@ -60,6 +150,153 @@ trait UserService {
         #[body] user: &User,
         #[header(COOKIE)] cookie: &str,
     ) -> Result<Response<User>, Self::Error>;
+
+    #[post("/api/user/compressed", mime::APPLICATION_JSON)]
+    #[compress(gzip)]
+    #[expect(201, mime::APPLICATION_JSON)]
+    async fn post_user_compressed(
+        &self,
+        #[body] user: &User,
+    ) -> Result<Response<User>, Self::Error>;
+
+    #[post("/api/session")]
+    async fn login(&self) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/session")]
+    async fn session(&self) -> Result<Response<()>, Self::Error>;
+
+    #[post("/api/counter")]
+    #[retry]
+    async fn increase(&self) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/slow")]
+    #[timeout(ms = 10)]
+    async fn slow(&self) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/slow")]
+    async fn slow_default(&self) -> Result<Response<()>, Self::Error>;
+
+    #[delete("/api/user/{id}")]
+    #[expect(200 | 204)]
+    async fn delete_user(&self, id: u64) -> Result<Response<()>, Self::Error>;
+
+    #[delete("/api/user/{id}")]
+    #[expect(2xx)]
+    async fn delete_user_any(&self, id: u64) -> Result<Response<()>, Self::Error>;
+}
+
+#[derive(Serialize, Deserialize, Debug, Eq, PartialEq)]
+struct ApiError {
+    code: u16,
+    message: String,
+}
+
+#[http_service]
+#[error_body(ApiError, mime::APPLICATION_JSON)]
+trait AdminService {
+    type Error;
+
+    #[get("/api/admin/{id}")]
+    async fn get_admin(&self, id: u64) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/admin/{id}")]
+    #[error_body(ApiError, mime::APPLICATION_MSGPACK)]
+    async fn get_admin_msgpack(&self, id: u64) -> Result<Response<()>, Self::Error>;
+}
+
+#[http_service]
+#[auth(bearer)]
+trait SecureService {
+    type Error;
+
+    #[get("/api/secure/bearer")]
+    async fn bearer(&self) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/secure/basic")]
+    #[auth(basic)]
+    async fn basic(&self) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/secure/api_key")]
+    #[auth(api_key, header = "X-Api-Key")]
+    async fn api_key(&self) -> Result<Response<()>, Self::Error>;
+
+    #[get("/api/secure/public")]
+    #[auth(none)]
+    async fn public(&self) -> Result<Response<()>, Self::Error>;
+}
+
+async fn secure_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    let header = |name: &str| req.headers().get(name).map(|value| value.to_str().unwrap());
+    let authorized = match req.uri().path() {
+        "/api/secure/bearer" => header("authorization") == Some("Bearer token"),
+        "/api/secure/basic" => header("authorization") == Some("Basic aGV4aTpwYXNzd29yZA=="),
+        "/api/secure/api_key" => {
+            header("X-Api-Key") == Some("key") && header("authorization").is_none()
+        }
+        _ => header("authorization").is_none(),
+    };
+    let status = if authorized { 200 } else { 401 };
+    Ok(Response::builder().status(status).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_auth() -> Result<(), Error> {
+    let mut credentials = HashMap::new();
+    credentials.insert(AuthScheme::Bearer, Credential::Bearer("token".into()));
+    credentials.insert(
+        AuthScheme::Basic,
+        Credential::Basic {
+            username: "hexi".into(),
+            password: Some("password".into()),
+        },
+    );
+    credentials.insert(AuthScheme::ApiKey, Credential::ApiKey("key".into()));
+    let service = Client::new(MOCK_BASE_URL.parse()?, secure_handler);
+    assert_eq!(200, service.public().await?.status());
+    match service.bearer().await {
+        Err(Error::AuthError(AuthError::NoProvider)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let service = service.with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_credential_provider(credentials.clone()),
+    );
+    assert_eq!(200, service.bearer().await?.status());
+    assert_eq!(200, service.basic().await?.status());
+    assert_eq!(200, service.api_key().await?.status());
+    assert_eq!(200, service.public().await?.status());
+    let service = service.with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_header(AUTHORIZATION, HeaderValue::from_static("Bearer default"))
+            .with_credential_provider(credentials),
+    );
+    assert_eq!(200, service.bearer().await?.status());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_refreshing_token() -> Result<(), Error> {
+    let fetched = Arc::new(AtomicUsize::new(0));
+    let counter = fetched.clone();
+    let provider = RefreshingToken::new(move || {
+        counter.fetch_add(1, Ordering::SeqCst);
+        async { Ok(Token::new("token").with_expires_in(Duration::from_millis(50))) }
+    })
+    .with_leeway(Duration::from_millis(0));
+    let service = Client::new(MOCK_BASE_URL.parse()?, secure_handler).with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_credential_provider(provider),
+    );
+    assert_eq!(200, service.bearer().await?.status());
+    assert_eq!(200, service.bearer().await?.status());
+    assert_eq!(1, fetched.load(Ordering::SeqCst));
+    Delay::new(Duration::from_millis(60)).await;
+    assert_eq!(200, service.bearer().await?.status());
+    assert_eq!(2, fetched.load(Ordering::SeqCst));
+    Ok(())
 }
 
 async fn ping_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
@@ -109,6 +346,270 @@ async fn test_get_user() -> Result<(), Error> {
     Ok(())
 }
 
+#[cfg(feature = "cassette")]
+#[tokio::test]
+async fn test_cassette() -> Result<(), Error> {
+    let recorder = Recorder::new();
+    let service =
+        Client::new(MOCK_BASE_URL.parse()?, get_user_handler).with_middleware(recorder.clone());
+    let recorded = service.get_user(0).await?;
+    let path = std::env::temp_dir().join("interfacer-http-get-user.yaml");
+    recorder.save(&path)?;
+    let service = Replay::new(MOCK_BASE_URL.parse()?, Cassette::load(&path)?);
+    let replayed = service.get_user(0).await?;
+    assert_eq!(recorded.status(), replayed.status());
+    assert_eq!(recorded.body(), replayed.body());
+    match service.get_user(1).await {
+        Err(Error::Unmatched(unmatched)) => {
+            assert_eq!("GET https://mock.rs/api/user/1", unmatched.request)
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_mock_server() -> Result<(), Error> {
+    let user = User {
+        name: "hexi".to_string(),
+        age: 20,
+    };
+    let service = MockServer::new(MOCK_BASE_URL.parse()?).with_expectation(
+        Expectation::new(Method::GET, "/api/user/{id}")
+            .with_header(ACCEPT, HeaderValue::from_static("application/json"))
+            .with_times(2)
+            .with_stub(Stub::new(200).with_content(&user, &mime::APPLICATION_JSON)?),
+    );
+    assert_eq!(&user, service.get_user(0).await?.body());
+    assert_eq!(user, service.get_user_body(1).await?);
+    Ok(())
+}
+
+type UserStore = Arc<Mutex<HashMap<u64, User>>>;
+
+fn user_id(req: &Request<Vec<u8>>) -> u64 {
+    Params::of(req).get("id").unwrap().parse().unwrap()
+}
+
+fn json_response(user: &User) -> Result<Response<Vec<u8>>, Error> {
+    Ok(Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(user.to_content(&mime::APPLICATION_JSON)?)?)
+}
+
+fn user_router(store: UserStore) -> Router {
+    let (get_store, put_store, delete_store) = (store.clone(), store.clone(), store);
+    Router::new()
+        .with_route(Method::GET, "/api/user/{id}", move |req| {
+            let resp = match get_store.lock().unwrap().get(&user_id(&req)) {
+                Some(user) => json_response(user),
+                None => Response::builder()
+                    .status(404)
+                    .body(Vec::new())
+                    .map_err(Error::from),
+            };
+            async { resp }
+        })
+        .with_route(Method::PUT, "/api/user/{id}", move |req| {
+            let id = user_id(&req);
+            let resp = req
+                .into_body()
+                .content_into(&mime::APPLICATION_JSON)
+                .map_err(Error::from)
+                .and_then(|user: User| {
+                    let resp = json_response(&user);
+                    put_store.lock().unwrap().insert(id, user);
+                    resp
+                });
+            async { resp }
+        })
+        .with_route(Method::DELETE, "/api/user/{id}", move |req| {
+            let status = match delete_store.lock().unwrap().remove(&user_id(&req)) {
+                Some(_) => 204,
+                None => 404,
+            };
+            async move { Ok(Response::builder().status(status).body(Vec::new())?) }
+        })
+}
+
+#[tokio::test]
+async fn test_router() -> Result<(), Error> {
+    let store = UserStore::default();
+    let router = user_router(store.clone());
+    let service = Client::new(MOCK_BASE_URL.parse()?, move |req| router.handle(req));
+    let user = User {
+        name: "hexi".to_string(),
+        age: 20,
+    };
+    assert!(service.get_user(0).await.is_err());
+    assert_eq!(
+        &user,
+        service.put_user(0, &user, DEFAULT_COOKIE).await?.body()
+    );
+    assert_eq!(&user, service.get_user(0).await?.body());
+    assert_eq!(1, store.lock().unwrap().len());
+    assert_eq!(204, service.delete_user(0).await?.status());
+    assert!(service.delete_user(0).await.is_err());
+    assert!(service.get_user(0).await.is_err());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_return_types() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_user_handler);
+    let user = User {
+        name: "hexi".to_string(),
+        age: 20,
+    };
+    assert_eq!(user, service.get_user_body(0).await?);
+    assert_eq!(
+        (StatusCode::OK, user),
+        service.get_user_with_status(0).await?
+    );
+    let parts = service.get_user_parts(0).await?;
+    assert_eq!(StatusCode::OK, parts.status);
+    assert_eq!(
+        mime::APPLICATION_JSON.as_ref(),
+        parts.headers.get(CONTENT_TYPE).unwrap()
+    );
+    Ok(())
+}
+
+async fn negotiate_user_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    let user = User {
+        name: "hexi".to_string(),
+        age: 20,
+    };
+    let (content_type, body) = match req.headers().get(ACCEPT).unwrap().to_str().unwrap() {
+        "application/msgpack, application/json;q=0.9" => (
+            mime::APPLICATION_MSGPACK.to_string(),
+            user.to_content(&mime::APPLICATION_MSGPACK)?,
+        ),
+        "application/json" => (
+            "application/json; charset=utf-8".to_string(),
+            user.to_content(&mime::APPLICATION_JSON)?,
+        ),
+        _ => (mime::TEXT_PLAIN.to_string(), b"hexi".to_vec()),
+    };
+    Ok(Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, content_type.as_str())
+        .body(body)?)
+}
+
+#[tokio::test]
+async fn test_content_negotiation() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, negotiate_user_handler);
+    let user = User {
+        name: "hexi".to_string(),
+        age: 20,
+    };
+    let resp = service.negotiate_user(0, None).await?;
+    assert_eq!(
+        mime::APPLICATION_MSGPACK,
+        resp.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap()
+    );
+    assert_eq!(&user, resp.body());
+    let resp = service.negotiate_user(0, Some("application/json")).await?;
+    assert_eq!(&user, resp.body());
+    assert!(service.negotiate_user(0, Some("text/plain")).await.is_err());
+    // negotiated `Accept` replaces the default one.
+    let service = service.with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_header(ACCEPT, HeaderValue::from_static("text/plain")),
+    );
+    let resp = service.negotiate_user(0, None).await?;
+    assert_eq!(
+        mime::APPLICATION_MSGPACK,
+        resp.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap()
+    );
+    assert_eq!(&user, resp.body());
+    Ok(())
+}
+
+async fn get_user_by_name_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!("GET", req.method());
+    let name = req
+        .uri()
+        .path()
+        .trim_start_matches("/api/user/name/")
+        .to_owned();
+    assert!(!name.contains('/'));
+    Ok(Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(User { name, age: 20 }.to_content(&mime::APPLICATION_JSON)?)?)
+}
+
+#[tokio::test]
+async fn test_path_segment_encoding() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_user_by_name_handler);
+    for (raw, encoded) in &[
+        ("hexi", "hexi"),
+        ("a/b c", "a%2Fb%20c"),
+        ("../admin", "..%2Fadmin"),
+        ("..?admin=true#", "..%3Fadmin=true%23"),
+        ("%2e%2e", "%252e%252e"),
+    ] {
+        let resp = service.get_user_by_name(raw).await?;
+        assert_eq!(200, resp.status());
+        assert_eq!(encoded, &resp.body().name);
+    }
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_dot_segment_rejected() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_user_by_name_handler);
+    for dot in &[".", ".."] {
+        match service.get_user_by_name(dot).await {
+            Err(Error::PathSegmentError(_)) => (),
+            other => panic!("dot segment should be rejected: {:?}", other),
+        }
+    }
+    Ok(())
+}
+
+async fn get_file_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!(
+        Url::parse(MOCK_BASE_URL)?
+            .join("/api/file/dir/file%20name.txt")?
+            .as_str(),
+        req.uri()
+    );
+    assert_eq!("GET", req.method());
+    Ok(Response::builder().status(200).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_raw_path_template() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_file_handler);
+    let resp = service.get_file("dir/file%20name.txt").await?;
+    assert_eq!(200, resp.status());
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_middleware() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_user_by_name_handler).with_middleware(
+        map_request(|mut req| {
+            *req.uri_mut() = req
+                .uri()
+                .to_string()
+                .replace("/api/user/name/hexi", "/api/user/name/boss")
+                .parse()
+                .unwrap();
+            req
+        }),
+    );
+    let resp = service.get_user_by_name("hexi").await?;
+    assert_eq!(200, resp.status());
+    assert_eq!("boss", &resp.body().name);
+    Ok(())
+}
+
 async fn get_users_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
     assert_eq!(
         Url::parse(MOCK_BASE_URL)?
@@ -156,6 +657,109 @@ async fn test_get_users() -> Result<(), Error> {
     Ok(())
 }
 
+async fn get_me_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!(
+        Url::parse(MOCK_BASE_URL)?.join("/api/me")?.as_str(),
+        req.uri()
+    );
+    let name = match req.headers().get(AUTHORIZATION) {
+        Some(token) => token.to_str().unwrap().to_owned(),
+        None => "anonymous".to_owned(),
+    };
+    let languages = req.headers().get_all(ACCEPT_LANGUAGE).iter().count();
+    let traces = req.headers().get_all("X-Trace").iter().count();
+    Ok(Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(
+            User {
+                name,
+                age: (languages * 10 + traces) as i32,
+            }
+            .to_content(&mime::APPLICATION_JSON)?,
+        )?)
+}
+
+#[tokio::test]
+async fn test_optional_and_multiple_headers() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_me_handler);
+    let resp = service.get_me(None, &[], Vec::new()).await?;
+    assert_eq!(
+        &User {
+            name: "anonymous".to_string(),
+            age: 0,
+        },
+        resp.body()
+    );
+    let resp = service
+        .get_me(
+            Some("Bearer hexi"),
+            &["zh-CN", "en"],
+            vec!["a".to_owned(), "b".to_owned(), "c".to_owned()],
+        )
+        .await?;
+    assert_eq!(
+        &User {
+            name: "Bearer hexi".to_string(),
+            age: 23,
+        },
+        resp.body()
+    );
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_override_default_headers() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, get_me_handler).with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_header(AUTHORIZATION, HeaderValue::from_static("Bearer default")),
+    );
+    let resp = service.get_me(None, &[], Vec::new()).await?;
+    assert_eq!("Bearer default", resp.body().name);
+    let resp = service.get_me(Some("Bearer hexi"), &[], Vec::new()).await?;
+    assert_eq!("Bearer hexi", resp.body().name);
+    Ok(())
+}
+
+async fn search_users_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!("GET", req.method());
+    let expect_uri = match req.uri().query() {
+        Some(query) if query.contains("age_max") => {
+            "/api/user?sort=age&age_max=40&tag=a+b&tag=c%26d&name=hexi"
+        }
+        _ => "/api/user?sort=name&age_min=18",
+    };
+    assert_eq!(
+        Url::parse(MOCK_BASE_URL)?.join(expect_uri)?.as_str(),
+        req.uri()
+    );
+    Ok(Response::builder()
+        .status(200)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(Vec::<User>::new().to_content(&mime::APPLICATION_JSON)?)?)
+}
+
+#[tokio::test]
+async fn test_search_users() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, search_users_handler);
+    let filter = Filter {
+        name: Some("hexi"),
+        age_min: None,
+    };
+    let resp = service
+        .search_users("age", Some(40), &["a b", "c&d"], &filter)
+        .await?;
+    assert_eq!(200, resp.status());
+    let filter = Filter {
+        name: None,
+        age_min: Some(18),
+    };
+    let resp = service.search_users("name", None, &[], &filter).await?;
+    assert_eq!(200, resp.status());
+    Ok(())
+}
+
 async fn put_user_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
     assert_eq!(
         Url::parse(MOCK_BASE_URL)?.join("/api/user/0")?.as_str(),
@@ -225,3 +829,386 @@ async fn test_post_user() -> Result<(), Error> {
     assert_eq!(&user, resp.body());
     Ok(())
 }
+
+#[cfg(feature = "compression")]
+async fn compressed_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    use flate2::{read::GzDecoder, write::GzEncoder};
+    use std::io::{Read, Write};
+    assert_eq!("gzip", req.headers()[CONTENT_ENCODING]);
+    assert_eq!("gzip, deflate, br", req.headers()[ACCEPT_ENCODING]);
+    let mut body = Vec::new();
+    GzDecoder::new(req.body().as_slice()).read_to_end(&mut body)?;
+    let user: User = body.content_into(&mime::APPLICATION_JSON)?;
+    let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(&user.to_content(&mime::APPLICATION_JSON)?)?;
+    Ok(Response::builder()
+        .status(201)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .header(CONTENT_ENCODING, "gzip")
+        .body(encoder.finish()?)?)
+}
+
+#[cfg(feature = "compression")]
+#[tokio::test]
+async fn test_compression() -> Result<(), Error> {
+    let service =
+        Client::new(MOCK_BASE_URL.parse()?, compressed_handler).with_middleware(Compression::new());
+    let user = User {
+        name: "hexi".to_string(),
+        age: 20,
+    };
+    let resp = service.post_user_compressed(&user).await?;
+    assert_eq!(201, resp.status());
+    assert!(!resp.headers().contains_key(CONTENT_ENCODING));
+    assert_eq!(&user, resp.body());
+    Ok(())
+}
+
+fn counter_handler(req: Request<Vec<u8>>, count: usize) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!("POST", req.method());
+    let status = match count {
+        0 => 503,
+        _ => 200,
+    };
+    Ok(Response::builder().status(status).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_retry() -> Result<(), Error> {
+    let calls = Arc::new(AtomicUsize::new(0));
+    let counter = calls.clone();
+    let handler = move |req| {
+        let count = counter.fetch_add(1, Ordering::SeqCst);
+        async move { counter_handler(req, count) }
+    };
+    let service = Client::new(MOCK_BASE_URL.parse()?, handler).with_middleware(
+        Retry::new().with_backoff(Duration::from_millis(1), Duration::from_millis(10)),
+    );
+    let resp = service.increase().await?;
+    assert_eq!(200, resp.status());
+    assert_eq!(2, calls.load(Ordering::SeqCst));
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fault_injection() -> Result<(), Error> {
+    let injection = |fault, calls| {
+        Injection::new(fault)
+            .with_route(Method::GET, "/api/user/{id}")
+            .with_calls(calls)
+    };
+    let service = FaultInjector::new(Client::new(MOCK_BASE_URL.parse()?, get_user_handler))
+        .with_injection(injection(
+            Fault::Status(StatusCode::SERVICE_UNAVAILABLE),
+            1..=1,
+        ))
+        .with_injection(injection(Fault::Disconnect, 2..=2))
+        .with_injection(injection(Fault::ContentType(mime::TEXT_PLAIN), 4..=4))
+        .with_injection(injection(Fault::Truncate(4), 5..=5))
+        .with_middleware(
+            Retry::new()
+                .with_max_attempts(3)
+                .with_backoff(Duration::from_millis(1), Duration::from_millis(10)),
+        );
+    assert_eq!(200, service.get_user(0).await?.status());
+    match service.get_user(0).await {
+        Err(Error::Unexpected(_)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match service.get_user(0).await {
+        Err(Error::FromContentError(_)) => (),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(200, service.get_user(0).await?.status());
+    Ok(())
+}
+
+async fn slow_handler(_req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    Delay::new(Duration::from_secs(10)).await;
+    Ok(Response::builder().status(200).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_timeout() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, slow_handler);
+    match service.slow().await {
+        Err(Error::Timeout(err)) => assert_eq!(Duration::from_millis(10), err.duration()),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    let service = service.with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_timeout(Duration::from_millis(20)),
+    );
+    match service.slow_default().await {
+        Err(Error::Timeout(err)) => assert_eq!(Duration::from_millis(20), err.duration()),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    Ok(())
+}
+
+async fn session_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    match req.method().as_str() {
+        "POST" => Ok(Response::builder()
+            .status(200)
+            .header(SET_COOKIE, "session=token; Path=/api; HttpOnly")
+            .body(Vec::new())?),
+        _ => {
+            let status = match req.headers().get(COOKIE) {
+                Some(cookie) if cookie == "session=token" => 200,
+                _ => 401,
+            };
+            Ok(Response::builder().status(status).body(Vec::new())?)
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_cookie_store() -> Result<(), Error> {
+    let store = CookieStore::new();
+    let service =
+        Client::new(MOCK_BASE_URL.parse()?, session_handler).with_middleware(store.clone());
+    assert!(service.session().await.is_err());
+    assert_eq!(200, service.login().await?.status());
+    assert_eq!(200, service.session().await?.status());
+    store.clear();
+    assert!(service.session().await.is_err());
+    Ok(())
+}
+
+async fn defaults_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!("https://mock.rs/?api_key=key", req.uri());
+    assert_eq!("tenant-0", req.headers()[USER_AGENT]);
+    assert_eq!(Version::HTTP_2, req.version());
+    Ok(Response::builder().status(200).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_helper_defaults() -> Result<(), Error> {
+    let api_key = String::from("key");
+    let agent = HeaderValue::from_static("tenant-0");
+    let service = Client::new(MOCK_BASE_URL.parse()?, defaults_handler).with_helper(
+        Helper::new()
+            .with_base_url(MOCK_BASE_URL.parse()?)
+            .with_header(USER_AGENT, agent)
+            .with_query("api_key", api_key)
+            .with_version(Version::HTTP_2),
+    );
+    assert_eq!(200, service.ping().await?.status());
+    Ok(())
+}
+
+async fn delete_user_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!("DELETE", req.method());
+    let status = match req.uri().path() {
+        "/api/user/0" => 200,
+        "/api/user/1" => 204,
+        _ => 202,
+    };
+    Ok(Response::builder().status(status).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_status_set() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, delete_user_handler);
+    assert_eq!(200, service.delete_user(0).await?.status());
+    assert_eq!(204, service.delete_user(1).await?.status());
+    match service.delete_user(2).await {
+        Err(Error::Unexpected(err)) => assert_eq!(
+            "Unexpected: status code should be 200 | 204",
+            &err.to_string()
+        ),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    assert_eq!(202, service.delete_user_any(2).await?.status());
+    Ok(())
+}
+
+async fn admin_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    let error = ApiError {
+        code: 404,
+        message: "admin not found".into(),
+    };
+    let resp = Response::builder()
+        .status(404)
+        .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+        .body(error.to_content(&mime::APPLICATION_JSON)?)?;
+    match req.uri().path() {
+        "/api/admin/0" => Ok(resp),
+        _ => Ok(Response::builder()
+            .status(500)
+            .body(b"internal error".to_vec())?),
+    }
+}
+
+#[tokio::test]
+async fn test_error_body() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, admin_handler);
+    let expect_error = ApiError {
+        code: 404,
+        message: "admin not found".into(),
+    };
+    match service.get_admin(0).await {
+        Err(Error::Unexpected(err)) => {
+            assert_eq!(404, err.response().status());
+            assert_eq!(
+                expect_error.to_content(&mime::APPLICATION_JSON)?,
+                *err.response().body()
+            );
+            assert!(err.body_type().unwrap().ends_with("ApiError"));
+            assert_eq!(
+                Some(err.body_type().unwrap()),
+                err.body::<String>().unwrap_err().actual
+            );
+            assert_eq!(expect_error, err.into_body::<ApiError>().unwrap()?);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match service.get_admin(1).await {
+        Err(Error::Unexpected(err)) => {
+            assert!(err.body::<ApiError>().unwrap().is_err());
+            assert_eq!(b"internal error".to_vec(), err.into_response().into_body());
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    match service.get_admin_msgpack(0).await {
+        Err(Error::Unexpected(err)) => assert!(err.body::<ApiError>().unwrap().is_err()),
+        other => panic!("unexpected result: {:?}", other),
+    }
+    Ok(())
+}
+
+const PROBLEM_JSON: &str = "application/problem+json; charset=utf-8";
+
+async fn problem_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    let (status, body) = match req.uri().path() {
+        "/api/user/0" => (
+            200,
+            User {
+                name: "hexi".into(),
+                age: 20,
+            }
+            .to_content(&mime::APPLICATION_JSON)?,
+        ),
+        _ => (
+            404,
+            ApiError {
+                code: 404,
+                message: "admin not found".into(),
+            }
+            .to_content(&mime::APPLICATION_JSON)?,
+        ),
+    };
+    Ok(Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, PROBLEM_JSON)
+        .body(body)?)
+}
+
+#[tokio::test]
+async fn test_suffix_mime_match() -> Result<(), Error> {
+    let helper = Helper::new()
+        .with_base_url(MOCK_BASE_URL.parse()?)
+        .with_mime_match(MimeMatch::Suffix);
+    let service = Client::new(MOCK_BASE_URL.parse()?, problem_handler).with_helper(helper);
+    let user = service.get_user_body(0).await?;
+    assert_eq!("hexi", user.name);
+    match service.get_admin(0).await {
+        Err(Error::Unexpected(err)) => {
+            assert_eq!(PROBLEM_JSON, err.response().headers()[CONTENT_TYPE]);
+            assert!(!err.response().body().is_empty());
+            assert_eq!(404, err.into_body::<ApiError>().unwrap()?.code);
+        }
+        other => panic!("unexpected result: {:?}", other),
+    }
+    Ok(())
+}
+
+async fn avatar_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    let content_type: mime::Mime = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    assert_eq!(mime::MULTIPART_FORM_DATA, content_type.essence_str());
+    let form = parse_multipart(&req)?;
+    assert_eq!(
+        Some(form.boundary()),
+        content_type.get_param(mime::BOUNDARY).map(|b| b.as_str())
+    );
+    assert_eq!(
+        Some("hexi"),
+        form.part("name").and_then(|part| part.as_text())
+    );
+    let avatar = form.part("avatar").unwrap();
+    assert_eq!(Some("avatar.png"), avatar.filename());
+    assert_eq!(Some(&mime::IMAGE_PNG), avatar.content_type());
+    assert_eq!(b"\x89PNG".to_vec(), avatar.data());
+    Ok(Response::builder().status(201).body(Vec::new())?)
+}
+
+#[tokio::test]
+async fn test_multipart() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, avatar_handler);
+    let form = Multipart::new().with_text("name", "hexi").with_file(
+        "avatar",
+        "avatar.png",
+        mime::IMAGE_PNG,
+        b"\x89PNG".to_vec(),
+    );
+    assert_eq!(
+        StatusCode::CREATED,
+        service.upload_avatar(&form).await?.status()
+    );
+    Ok(())
+}
+
+async fn file_handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+    assert_eq!(
+        Url::parse(MOCK_BASE_URL)?.join("/api/file/0")?.as_str(),
+        req.uri()
+    );
+    match req.method().as_str() {
+        "GET" => Ok(Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, mime::APPLICATION_OCTET_STREAM.as_ref())
+            .body(b"hello, world".to_vec())?),
+        "PUT" => {
+            assert_eq!(
+                mime::APPLICATION_OCTET_STREAM,
+                req.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap()
+            );
+            assert_eq!(b"hello, world".to_vec(), req.body().clone());
+            Ok(Response::builder()
+                .status(200)
+                .header(CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                .body((req.body().len() as u64).to_content(&mime::APPLICATION_JSON)?)?)
+        }
+        _ => Ok(Response::builder().status(405).body(Vec::new())?),
+    }
+}
+
+#[tokio::test]
+async fn test_download() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, file_handler);
+    let resp = service.download(0).await?;
+    assert_eq!(200, resp.status());
+    assert_eq!(b"hello, world".to_vec(), resp.into_body().into_vec().await?);
+    let body = service.download_body(0).await?;
+    assert_eq!(b"hello, world".to_vec(), body.into_vec().await?);
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_upload() -> Result<(), Error> {
+    let service = Client::new(MOCK_BASE_URL.parse()?, file_handler);
+    let chunks =
+        stream::iter(vec!["hello", ", ", "world"]).map(|chunk| Ok(chunk.as_bytes().into()));
+    let resp = service.upload(0, Body::wrap_stream(chunks)).await?;
+    assert_eq!(200, resp.status());
+    assert_eq!(&12, resp.body());
+    Ok(())
+}
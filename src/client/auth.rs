@@ -0,0 +1,314 @@
+use crate::{async_trait, http::HeaderValue, AuthError};
+use futures::future::Future;
+use futures::lock::Mutex;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Formatter};
+use std::time::{Duration, Instant};
+
+const BASE64_TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Authentication scheme of `#[auth]`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum AuthScheme {
+    /// `Authorization: Bearer <token>`
+    Bearer,
+    /// `Authorization: Basic <base64(username:password)>`
+    Basic,
+    /// `<header>: <key>`
+    ApiKey,
+}
+
+/// Credential resolved by `CredentialProvider`.
+#[derive(Clone, Eq, PartialEq)]
+pub enum Credential {
+    Bearer(String),
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    ApiKey(String),
+}
+
+impl Credential {
+    /// format as a sensitive header value.
+    pub fn to_header_value(&self) -> Result<HeaderValue, AuthError> {
+        let value = match self {
+            Credential::Bearer(token) => format!("Bearer {}", token),
+            Credential::Basic { username, password } => {
+                let pair = format!("{}:{}", username, password.as_deref().unwrap_or(""));
+                format!("Basic {}", base64(pair.as_bytes()))
+            }
+            Credential::ApiKey(key) => key.clone(),
+        };
+        let mut value = HeaderValue::from_str(&value).map_err(|_| AuthError::InvalidCredential)?;
+        value.set_sensitive(true);
+        Ok(value)
+    }
+}
+
+impl Debug for Credential {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Credential::Bearer(_) => f.write_str("Bearer(***)"),
+            Credential::Basic { username, .. } => {
+                f.debug_struct("Basic").field("username", username).finish()
+            }
+            Credential::ApiKey(_) => f.write_str("ApiKey(***)"),
+        }
+    }
+}
+
+/// Provider of credentials, bound by `Helper::with_credential_provider`.
+///
+/// It's called for each request of methods annotated by `#[auth]`.
+///
+/// ```rust
+/// use interfacer_http::{AuthScheme, Credential, Helper};
+/// use std::collections::HashMap;
+///
+/// let mut credentials = HashMap::new();
+/// credentials.insert(AuthScheme::Bearer, Credential::Bearer("token".into()));
+/// credentials.insert(AuthScheme::ApiKey, Credential::ApiKey("key".into()));
+/// let helper = Helper::new().with_credential_provider(credentials);
+/// ```
+#[async_trait]
+pub trait CredentialProvider: Send + Sync {
+    async fn credential(&self, scheme: AuthScheme) -> Result<Credential, AuthError>;
+}
+
+/// the same credential for every scheme.
+#[async_trait]
+impl CredentialProvider for Credential {
+    async fn credential(&self, _scheme: AuthScheme) -> Result<Credential, AuthError> {
+        Ok(self.clone())
+    }
+}
+
+#[async_trait]
+impl CredentialProvider for HashMap<AuthScheme, Credential> {
+    async fn credential(&self, scheme: AuthScheme) -> Result<Credential, AuthError> {
+        self.get(&scheme)
+            .cloned()
+            .ok_or(AuthError::NoCredential { scheme })
+    }
+}
+
+/// Bearer token with an optional lifetime.
+#[derive(Clone)]
+pub struct Token {
+    value: String,
+    expires_at: Option<Instant>,
+}
+
+impl Token {
+    /// Construct a token never expires.
+    pub fn new(value: impl Into<String>) -> Self {
+        Self {
+            value: value.into(),
+            expires_at: None,
+        }
+    }
+
+    /// expire after `expires_in`.
+    pub fn with_expires_in(self, expires_in: Duration) -> Self {
+        Self {
+            expires_at: Some(Instant::now() + expires_in),
+            ..self
+        }
+    }
+
+    /// the token string.
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// whether the token expires within `leeway`.
+    pub fn is_expired(&self, leeway: Duration) -> bool {
+        match self.expires_at {
+            Some(expires_at) => Instant::now() + leeway >= expires_at,
+            None => false,
+        }
+    }
+}
+
+impl Debug for Token {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Token")
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
+
+/// Bearer token provider caching the token, and fetching a new one when it expires.
+///
+/// Concurrent requests wait for a single fetch.
+///
+/// ```rust
+/// use interfacer_http::{AuthError, Helper, RefreshingToken, Token};
+/// use std::time::Duration;
+///
+/// let provider = RefreshingToken::new(|| async {
+///     // request a new token from the auth server.
+///     Ok::<_, AuthError>(Token::new("token").with_expires_in(Duration::from_secs(3600)))
+/// });
+/// let helper = Helper::new().with_credential_provider(provider);
+/// ```
+pub struct RefreshingToken<F> {
+    fetch: F,
+    leeway: Duration,
+    token: Mutex<Option<Token>>,
+}
+
+impl<F, Fut> RefreshingToken<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Token, AuthError>> + Send,
+{
+    /// Construct with a token fetcher, leeway is 10s by default.
+    pub fn new(fetch: F) -> Self {
+        Self {
+            fetch,
+            leeway: Duration::from_secs(10),
+            token: Mutex::new(None),
+        }
+    }
+
+    /// refresh tokens expiring within `leeway`.
+    pub fn with_leeway(self, leeway: Duration) -> Self {
+        Self { leeway, ..self }
+    }
+
+    /// the cached token, fetch a new one if it's absent or expired.
+    pub async fn token(&self) -> Result<String, AuthError> {
+        let mut token = self.token.lock().await;
+        match token.as_ref() {
+            Some(cached) if !cached.is_expired(self.leeway) => Ok(cached.value.clone()),
+            _ => {
+                let fetched = (self.fetch)().await?;
+                let value = fetched.value.clone();
+                *token = Some(fetched);
+                Ok(value)
+            }
+        }
+    }
+
+    /// drop the cached token, like when it's rejected by the server.
+    pub async fn invalidate(&self) {
+        *self.token.lock().await = None;
+    }
+}
+
+#[async_trait]
+impl<F, Fut> CredentialProvider for RefreshingToken<F>
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Token, AuthError>> + Send,
+{
+    async fn credential(&self, _scheme: AuthScheme) -> Result<Credential, AuthError> {
+        Ok(Credential::Bearer(self.token().await?))
+    }
+}
+
+fn base64(data: &[u8]) -> String {
+    let mut encoded = String::with_capacity(data.len() * 4 / 3 + 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).cloned().unwrap_or(0),
+            chunk.get(2).cloned().unwrap_or(0),
+        ];
+        let indexes = [
+            bytes[0] >> 2,
+            (bytes[0] & 0x03) << 4 | bytes[1] >> 4,
+            (bytes[1] & 0x0f) << 2 | bytes[2] >> 6,
+            bytes[2] & 0x3f,
+        ];
+        for (i, index) in indexes.iter().enumerate() {
+            if i <= chunk.len() {
+                encoded.push(BASE64_TABLE[*index as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{base64, AuthScheme, Credential, CredentialProvider, RefreshingToken, Token};
+    use crate::AuthError;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn test_base64() {
+        assert_eq!("", base64(b""));
+        assert_eq!("Zg==", base64(b"f"));
+        assert_eq!("Zm8=", base64(b"fo"));
+        assert_eq!("Zm9v", base64(b"foo"));
+        assert_eq!("Zm9vYmFy", base64(b"foobar"));
+    }
+
+    #[test]
+    fn header_value() -> Result<(), AuthError> {
+        assert_eq!(
+            "Bearer token",
+            Credential::Bearer("token".into()).to_header_value()?
+        );
+        let basic = Credential::Basic {
+            username: "Aladdin".into(),
+            password: Some("open sesame".into()),
+        };
+        assert_eq!(
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==",
+            basic.to_header_value()?
+        );
+        assert!(basic.to_header_value()?.is_sensitive());
+        assert_eq!("key", Credential::ApiKey("key".into()).to_header_value()?);
+        assert!(Credential::ApiKey("\n".into()).to_header_value().is_err());
+        assert_eq!(
+            "Bearer(***)",
+            format!("{:?}", Credential::Bearer("token".into()))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn credential_map() {
+        let mut credentials = HashMap::new();
+        credentials.insert(AuthScheme::ApiKey, Credential::ApiKey("key".into()));
+        assert_eq!(
+            Credential::ApiKey("key".into()),
+            block_on(credentials.credential(AuthScheme::ApiKey)).unwrap()
+        );
+        match block_on(credentials.credential(AuthScheme::Bearer)) {
+            Err(AuthError::NoCredential { scheme }) => assert_eq!(AuthScheme::Bearer, scheme),
+            other => panic!("unexpected result: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn refreshing_token() -> Result<(), AuthError> {
+        let fetched = AtomicUsize::new(0);
+        let provider = RefreshingToken::new(|| {
+            let count = fetched.fetch_add(1, Ordering::SeqCst);
+            async move {
+                Ok(Token::new(format!("token-{}", count)).with_expires_in(Duration::from_secs(60)))
+            }
+        });
+        assert_eq!("token-0", block_on(provider.token())?);
+        assert_eq!("token-0", block_on(provider.token())?);
+        block_on(provider.invalidate());
+        assert_eq!("token-1", block_on(provider.token())?);
+        let provider = provider.with_leeway(Duration::from_secs(120));
+        assert_eq!("token-2", block_on(provider.token())?);
+        assert_eq!(
+            Credential::Bearer("token-3".into()),
+            block_on(provider.credential(AuthScheme::Bearer))?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,146 @@
+use bytes::Bytes;
+use futures::io::AsyncRead;
+use futures::stream::{self, Stream, StreamExt, TryStreamExt};
+use std::fmt::{self, Debug, Formatter};
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// Streaming body of `Request` or `Response`.
+///
+/// It's a `Stream` of `Bytes` chunks, used by `HttpClient::request_stream`,
+/// so that large bodies are not buffered into memory.
+///
+/// ```rust
+/// use interfacer_http::Body;
+/// use futures::{executor::block_on, stream};
+///
+/// let chunks = vec![Ok(b"hello, "[..].into()), Ok(b"world"[..].into())];
+/// let body = Body::wrap_stream(stream::iter(chunks));
+/// assert_eq!(b"hello, world".to_vec(), block_on(body.into_vec()).unwrap());
+/// ```
+pub struct Body {
+    inner: Pin<Box<dyn Stream<Item = io::Result<Bytes>> + Send + Sync>>,
+}
+
+impl Body {
+    /// Construct an empty body.
+    pub fn empty() -> Self {
+        Self::wrap_stream(stream::empty())
+    }
+
+    /// Wrap a stream of chunks.
+    pub fn wrap_stream<S>(stream: S) -> Self
+    where
+        S: Stream<Item = io::Result<Bytes>> + Send + Sync + 'static,
+    {
+        Self {
+            inner: Box::pin(stream),
+        }
+    }
+
+    /// Buffer all chunks into memory.
+    pub async fn into_vec(mut self) -> io::Result<Vec<u8>> {
+        let mut data = Vec::new();
+        while let Some(chunk) = self.next().await {
+            data.extend_from_slice(&chunk?);
+        }
+        Ok(data)
+    }
+
+    /// Convert into an `AsyncRead`.
+    pub fn into_async_read(self) -> impl AsyncRead + Send + Sync + Unpin {
+        TryStreamExt::into_async_read(self)
+    }
+}
+
+impl Default for Body {
+    fn default() -> Self {
+        Self::empty()
+    }
+}
+
+impl Stream for Body {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.inner.as_mut().poll_next(cx)
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(data: Vec<u8>) -> Self {
+        if data.is_empty() {
+            Self::empty()
+        } else {
+            Self::wrap_stream(stream::iter(Some(Ok(data.into()))))
+        }
+    }
+}
+
+impl From<Bytes> for Body {
+    fn from(data: Bytes) -> Self {
+        Self::wrap_stream(stream::iter(Some(Ok(data))))
+    }
+}
+
+impl Debug for Body {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Body").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Body;
+    use futures::executor::block_on;
+    use futures::io::AsyncReadExt;
+    use futures::stream::{self, StreamExt};
+    use std::io;
+
+    #[test]
+    fn empty() -> io::Result<()> {
+        assert!(block_on(Body::empty().next()).is_none());
+        assert!(block_on(Body::from(Vec::new()).into_vec())?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn chunks() -> io::Result<()> {
+        let body = Body::wrap_stream(stream::iter(vec![
+            Ok(b"a"[..].into()),
+            Ok(b"bc"[..].into()),
+        ]));
+        let chunks = block_on(body.collect::<Vec<_>>());
+        assert_eq!(2, chunks.len());
+        assert_eq!(
+            b"abc".to_vec(),
+            block_on(Body::from(b"abc".to_vec()).into_vec())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn error() {
+        let body = Body::wrap_stream(stream::iter(vec![
+            Ok(b"a"[..].into()),
+            Err(io::Error::new(io::ErrorKind::ConnectionReset, "reset")),
+        ]));
+        assert_eq!(
+            io::ErrorKind::ConnectionReset,
+            block_on(body.into_vec()).unwrap_err().kind()
+        );
+    }
+
+    #[test]
+    fn async_read() -> io::Result<()> {
+        let mut data = String::new();
+        block_on(
+            Body::from(b"hello".to_vec())
+                .into_async_read()
+                .read_to_string(&mut data),
+        )?;
+        assert_eq!("hello", &data);
+        Ok(())
+    }
+}
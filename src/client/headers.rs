@@ -0,0 +1,33 @@
+use crate::http::{request::Builder as RequestBuilder, HeaderMap};
+
+/// insert `headers` into `builder`, replacing existing values of the same names.
+///
+/// values of the same name in `headers` are all kept.
+///
+/// ```rust
+/// use interfacer_http::{replace_headers, Helper};
+/// use interfacer_http::http::{header::USER_AGENT, HeaderMap, HeaderValue};
+/// let mut builder = Helper::new()
+///     .with_header(USER_AGENT, HeaderValue::from_static("default"))
+///     .request();
+/// let mut headers = HeaderMap::new();
+/// headers.insert(USER_AGENT, HeaderValue::from_static("custom"));
+/// replace_headers(&mut builder, headers);
+/// let req = builder.body(()).unwrap();
+/// assert_eq!(vec!["custom"], req.headers().get_all(USER_AGENT).iter().collect::<Vec<_>>());
+/// ```
+pub fn replace_headers(builder: &mut RequestBuilder, headers: HeaderMap) {
+    // `headers_mut` is None if the builder has failed, the error is returned by `body`.
+    if let Some(map) = builder.headers_mut() {
+        let mut name = None;
+        for (key, value) in headers {
+            if let Some(key) = key {
+                map.remove(&key);
+                name = Some(key);
+            }
+            if let Some(name) = &name {
+                map.append(name.clone(), value);
+            }
+        }
+    }
+}
@@ -1,9 +1,16 @@
+use super::{AuthScheme, CredentialProvider, MimeMatch};
 use crate::{
     http::request::Builder as RequestBuilder,
-    http::HeaderValue,
+    http::{header::HeaderName, HeaderMap, HeaderValue, Version},
     mime::Mime,
     url::{ParseError, Url},
+    AuthError,
 };
+use std::sync::Arc;
+use std::time::Duration;
+
+type RequestInitializer = Arc<dyn Fn() -> RequestBuilder + Send + Sync>;
+type MimeMatcher = Arc<dyn Fn(&Mime, &HeaderValue) -> bool + Send + Sync>;
 
 /// Client helper.
 ///
@@ -18,21 +25,44 @@ use crate::{
 ///
 /// ```rust
 /// use interfacer_http::Helper;
-/// use interfacer_http::http::{Version, header::USER_AGENT, request::Builder as RequestBuilder};
+/// use interfacer_http::http::{Version, header::{HeaderValue, USER_AGENT}};
+/// let api_key = std::env::var("API_KEY").unwrap_or_default();
+/// let helper = Helper::new()
+///     .with_version(Version::HTTP_10)
+///     .with_header(USER_AGENT, HeaderValue::from_static("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/76.0.3809.100 Safari/537.36"))
+///     .with_query("api_key", api_key);
+/// ```
+///
+/// ### Custom request initializer
+///
+/// ```rust
+/// use interfacer_http::Helper;
+/// use interfacer_http::http::{header::USER_AGENT, request::Builder as RequestBuilder};
+/// let tenant = String::from("tenant-0");
 /// let helper = Helper::new()
-///     .with_request_initializer(|| {
+///     .with_request_initializer(move || {
 ///         let mut builder = RequestBuilder::new();
-///         builder
-///             .version(Version::HTTP_10)
-///             .header(USER_AGENT, "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_14_5) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/76.0.3809.100 Safari/537.36");
+///         builder.header(USER_AGENT, format!("interfacer-http ({})", tenant).as_str());
 ///         builder
 ///     });
 /// ```
+///
+/// ### Lenient mime matching
+///
+/// ```rust
+/// use interfacer_http::{Helper, MimeMatch};
+/// let helper = Helper::new().with_mime_match(MimeMatch::Suffix);
+/// ```
 #[derive(Clone)]
 pub struct Helper {
     base_url: Option<Url>,
-    request_initializer: fn() -> RequestBuilder,
-    mime_matcher: fn(&Mime, &HeaderValue) -> bool,
+    request_initializer: RequestInitializer,
+    mime_matcher: MimeMatcher,
+    timeout: Option<Duration>,
+    headers: HeaderMap,
+    queries: Vec<(String, String)>,
+    version: Option<Version>,
+    credential_provider: Option<Arc<dyn CredentialProvider>>,
 }
 
 impl Helper {
@@ -40,15 +70,24 @@ impl Helper {
     pub fn new() -> Self {
         Self {
             base_url: None,
-            request_initializer: RequestBuilder::new,
-            mime_matcher: |expect, actual| match actual.to_str() {
-                Ok(value) => expect == &value,
-                Err(_) => false,
-            },
+            request_initializer: Arc::new(RequestBuilder::new),
+            mime_matcher: Arc::new(|expect, actual| match_mime(MimeMatch::Essence, expect, actual)),
+            timeout: None,
+            headers: HeaderMap::new(),
+            queries: Vec::new(),
+            version: None,
+            credential_provider: None,
         }
     }
 }
 
+fn match_mime(mime_match: MimeMatch, expect: &Mime, actual: &HeaderValue) -> bool {
+    match actual.to_str().ok().and_then(|value| value.parse().ok()) {
+        Some(actual) => mime_match.matches(expect, &actual),
+        None => false,
+    }
+}
+
 impl Default for Helper {
     fn default() -> Self {
         Self::new()
@@ -65,17 +104,73 @@ impl Helper {
     }
 
     /// bind a request initializer.
-    pub fn with_request_initializer(self, request_initializer: fn() -> RequestBuilder) -> Self {
+    ///
+    /// default headers, queries and version are applied after it.
+    pub fn with_request_initializer(
+        self,
+        request_initializer: impl Fn() -> RequestBuilder + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            request_initializer: Arc::new(request_initializer),
+            ..self
+        }
+    }
+
+    /// append a default header to every request.
+    ///
+    /// headers from parameters replace default headers of the same name.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// append a default query pair to every request.
+    pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.queries.push((key.into(), value.into()));
+        self
+    }
+
+    /// bind a default http version.
+    pub fn with_version(self, version: Version) -> Self {
         Self {
-            request_initializer,
+            version: Some(version),
             ..self
         }
     }
 
     /// bind a mime matcher.
-    pub fn with_mime_matcher(self, mime_matcher: fn(&Mime, &HeaderValue) -> bool) -> Self {
+    pub fn with_mime_matcher(
+        self,
+        mime_matcher: impl Fn(&Mime, &HeaderValue) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            mime_matcher: Arc::new(mime_matcher),
+            ..self
+        }
+    }
+
+    /// bind a built-in mime matcher, `MimeMatch::Essence` by default.
+    pub fn with_mime_match(self, mime_match: MimeMatch) -> Self {
+        self.with_mime_matcher(move |expect, actual| match_mime(mime_match, expect, actual))
+    }
+
+    /// bind a default timeout of `http_service` methods.
+    ///
+    /// it can be overridden by `#[timeout]` for each method.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self {
+            timeout: Some(timeout),
+            ..self
+        }
+    }
+
+    /// bind a credential provider for methods annotated by `#[auth]`.
+    pub fn with_credential_provider(
+        self,
+        credential_provider: impl CredentialProvider + 'static,
+    ) -> Self {
         Self {
-            mime_matcher,
+            credential_provider: Some(Arc::new(credential_provider)),
             ..self
         }
     }
@@ -84,16 +179,42 @@ impl Helper {
     ///
     /// if `self.base_url` is None, `raw_url` will be parsed as `raw_url.parse()`.
     /// else, `raw_url` will be parsed as path.
+    ///
+    /// default queries are appended.
     pub fn parse_uri(&self, raw_url: &str) -> Result<Url, ParseError> {
-        match self.base_url {
-            Some(ref base_url) => base_url.join(raw_url),
-            None => raw_url.parse(),
+        let mut url: Url = match self.base_url {
+            Some(ref base_url) => base_url.join(raw_url)?,
+            None => raw_url.parse()?,
+        };
+        if !self.queries.is_empty() {
+            url.query_pairs_mut().extend_pairs(&self.queries);
         }
+        Ok(url)
     }
 
-    /// initialize a `Request`.
+    /// initialize a `Request` with default version and headers.
     pub fn request(&self) -> RequestBuilder {
-        (self.request_initializer)()
+        let mut builder = (self.request_initializer)();
+        if let Some(version) = self.version {
+            builder.version(version);
+        }
+        for (name, value) in self.headers.iter() {
+            builder.header(name, value.clone());
+        }
+        builder
+    }
+
+    /// default timeout, None means no timeout.
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// resolve credential of `scheme` as a header value.
+    pub async fn authorize(&self, scheme: AuthScheme) -> Result<HeaderValue, AuthError> {
+        match &self.credential_provider {
+            Some(provider) => provider.credential(scheme).await?.to_header_value(),
+            None => Err(AuthError::NoProvider),
+        }
     }
 
     /// util function to compare headers.
@@ -104,8 +225,12 @@ impl Helper {
 
 #[cfg(test)]
 mod tests {
-    use super::{Helper, ParseError, RequestBuilder};
-    use crate::http::{header::USER_AGENT, Error, Version};
+    use super::{Duration, Helper, MimeMatch, ParseError, RequestBuilder};
+    use crate::http::{
+        header::{ACCEPT, AUTHORIZATION, USER_AGENT},
+        Error, HeaderMap, HeaderValue, Version,
+    };
+    use crate::{mime, replace_headers};
 
     #[test]
     fn test_with_request_initializer() -> Result<(), Error> {
@@ -124,6 +249,91 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn with_defaults() -> Result<(), Error> {
+        let agent = String::from("tenant-0");
+        let helper = Helper::new()
+            .with_request_initializer(move || {
+                let mut builder = RequestBuilder::new();
+                builder.header(USER_AGENT, agent.as_str());
+                builder
+            })
+            .with_version(Version::HTTP_2)
+            .with_header(ACCEPT, HeaderValue::from_static("text/html"))
+            .with_header(ACCEPT, HeaderValue::from_static("text/plain"));
+        let req = helper.request().body(())?;
+        assert_eq!(Version::HTTP_2, req.version());
+        assert_eq!("tenant-0", req.headers()[USER_AGENT]);
+        assert_eq!(
+            vec!["text/html", "text/plain"],
+            req.headers().get_all(ACCEPT).iter().collect::<Vec<_>>()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn override_defaults() -> Result<(), Error> {
+        let helper = Helper::new()
+            .with_header(AUTHORIZATION, HeaderValue::from_static("Bearer default"))
+            .with_header(USER_AGENT, HeaderValue::from_static("interfacer-http"));
+        let mut builder = helper.request();
+        let mut headers = HeaderMap::new();
+        headers.append(AUTHORIZATION, HeaderValue::from_static("Bearer token"));
+        replace_headers(&mut builder, headers);
+        let req = builder.body(())?;
+        assert_eq!(
+            vec!["Bearer token"],
+            req.headers()
+                .get_all(AUTHORIZATION)
+                .iter()
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("interfacer-http", req.headers()[USER_AGENT]);
+        Ok(())
+    }
+
+    #[test]
+    fn with_query() -> Result<(), ParseError> {
+        let helper = Helper::new()
+            .with_base_url("https://github.com".parse()?)
+            .with_query("key", "a b")
+            .with_query("lang", "rust");
+        assert_eq!(
+            "https://github.com/path?page=1&key=a+b&lang=rust",
+            helper.parse_uri("path?page=1")?.as_str()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn with_timeout() {
+        assert!(Helper::new().timeout().is_none());
+        let helper = Helper::new().with_timeout(Duration::from_secs(1));
+        assert_eq!(Some(Duration::from_secs(1)), helper.timeout());
+    }
+
+    #[test]
+    fn with_mime_matcher() {
+        let charset = HeaderValue::from_static("application/json; charset=utf-8");
+        let problem = HeaderValue::from_static("application/problem+json");
+        let helper = Helper::new();
+        assert!(helper.match_mime(&mime::APPLICATION_JSON, &charset));
+        assert!(!helper.match_mime(&mime::APPLICATION_JSON, &problem));
+        let helper = Helper::new().with_mime_match(MimeMatch::Exact);
+        assert!(!helper.match_mime(&mime::APPLICATION_JSON, &charset));
+        let helper = Helper::new().with_mime_match(MimeMatch::Suffix);
+        assert!(helper.match_mime(&mime::APPLICATION_JSON, &problem));
+        let accepted = [mime::APPLICATION_MSGPACK];
+        let helper = Helper::new().with_mime_matcher(move |_, actual| {
+            accepted.iter().any(|mime| actual == mime.as_ref())
+        });
+        assert!(!helper.match_mime(&mime::APPLICATION_JSON, &charset));
+        assert!(helper.match_mime(
+            &mime::APPLICATION_JSON,
+            &HeaderValue::from_static("application/msgpack")
+        ));
+    }
+
     #[test]
     fn with_base_url() -> Result<(), ParseError> {
         let helper = Helper::new().with_base_url("https://github.com".parse()?);
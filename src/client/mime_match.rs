@@ -0,0 +1,105 @@
+use crate::{
+    mime::{self, Mime},
+    MimeExt,
+};
+
+/// Built-in mime matchers, selected by `Helper::with_mime_match`.
+///
+/// Each one accepts everything the previous one accepts.
+///
+/// ```rust
+/// use interfacer_http::{mime, MimeMatch};
+///
+/// let actual = "application/problem+json; charset=utf-8".parse().unwrap();
+/// assert!(!MimeMatch::Essence.matches(&mime::APPLICATION_JSON, &actual));
+/// assert!(MimeMatch::Suffix.matches(&mime::APPLICATION_JSON, &actual));
+/// assert!(MimeMatch::Wildcard.matches(&"application/*".parse().unwrap(), &actual));
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum MimeMatch {
+    /// type, subtype, suffix and parameters are all equal.
+    Exact,
+    /// parameters like charset are ignored.
+    Essence,
+    /// `application/json` also matches `application/*+json`.
+    Suffix,
+    /// `application/*` or `*/*` matches any subtype.
+    Wildcard,
+}
+
+impl MimeMatch {
+    /// whether `actual` is acceptable as `expect`.
+    pub fn matches(self, expect: &Mime, actual: &Mime) -> bool {
+        match self {
+            MimeMatch::Exact => expect == actual,
+            MimeMatch::Essence => expect.pure_type() == actual.pure_type(),
+            MimeMatch::Suffix => {
+                MimeMatch::Essence.matches(expect, actual)
+                    || (expect.suffix().is_none()
+                        && expect.type_() == actual.type_()
+                        && actual.suffix().map(|suffix| suffix.as_str())
+                            == Some(expect.subtype().as_str()))
+            }
+            MimeMatch::Wildcard => {
+                MimeMatch::Suffix.matches(expect, actual)
+                    || expect.type_() == mime::STAR
+                    || (expect.subtype() == mime::STAR && expect.type_() == actual.type_())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MimeMatch;
+    use crate::mime::{self, Mime};
+
+    fn parse(raw: &str) -> Mime {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn exact() {
+        assert!(MimeMatch::Exact.matches(&mime::APPLICATION_JSON, &parse("application/json")));
+        assert!(!MimeMatch::Exact.matches(
+            &mime::APPLICATION_JSON,
+            &parse("application/json; charset=utf-8")
+        ));
+    }
+
+    #[test]
+    fn essence() {
+        assert!(MimeMatch::Essence.matches(
+            &mime::APPLICATION_JSON,
+            &parse("application/json; charset=utf-8")
+        ));
+        assert!(
+            MimeMatch::Essence.matches(&mime::TEXT_PLAIN_UTF_8, &parse("text/plain; charset=gbk"))
+        );
+        assert!(!MimeMatch::Essence
+            .matches(&mime::APPLICATION_JSON, &parse("application/problem+json")));
+    }
+
+    #[test]
+    fn suffix() {
+        assert!(MimeMatch::Suffix.matches(
+            &mime::APPLICATION_JSON,
+            &parse("application/problem+json; charset=utf-8")
+        ));
+        assert!(MimeMatch::Suffix.matches(&mime::TEXT_XML, &parse("text/atom+xml")));
+        assert!(!MimeMatch::Suffix.matches(&mime::TEXT_XML, &parse("application/atom+xml")));
+        assert!(
+            !MimeMatch::Suffix.matches(&parse("application/problem+json"), &mime::APPLICATION_JSON)
+        );
+    }
+
+    #[test]
+    fn wildcard() {
+        assert!(MimeMatch::Wildcard.matches(&mime::STAR_STAR, &mime::IMAGE_PNG));
+        assert!(MimeMatch::Wildcard.matches(&mime::IMAGE_STAR, &mime::IMAGE_PNG));
+        assert!(MimeMatch::Wildcard
+            .matches(&parse("application/*"), &parse("application/problem+json")));
+        assert!(!MimeMatch::Wildcard.matches(&mime::IMAGE_STAR, &mime::TEXT_PLAIN));
+        assert!(!MimeMatch::Wildcard.matches(&mime::IMAGE_PNG, &mime::IMAGE_STAR));
+    }
+}
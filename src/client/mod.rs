@@ -3,14 +3,27 @@ use crate::{
     http::{Request, Response},
     Error,
 };
+pub use auth::{AuthScheme, Credential, CredentialProvider, RefreshingToken, Token};
+pub use body::Body;
+pub use headers::replace_headers;
 pub use helper::Helper;
+pub use mime_match::MimeMatch;
+pub use options::{ContentEncoding, RequestOptions};
+#[cfg(any(feature = "serde-base", feature = "serde-full"))]
+pub use query::append_query;
 pub use response::{CookieError, ResponseExt};
+pub use status::StatusSet;
+pub use timeout::timeout;
+pub use uri::{encode_path_segment, encode_query_component};
 
-// TODO: use T: AsyncRead as type of Request::Body
-// TODO: use T: AsyncRead as type of Response::Body
 /// HttpClient trait.
 /// Should be implemented by other asynchronous http client.
 ///
+/// `request` sends a buffered body and returns a buffered body;
+/// `request_stream` sends and returns streaming `Body`,
+/// its default implementation buffers them and delegates to `request`,
+/// clients supporting streaming should override it.
+///
 /// ### Example
 ///
 /// ```rust,ignore
@@ -47,8 +60,26 @@ pub use response::{CookieError, ResponseExt};
 pub trait HttpClient: Sync {
     type Err: Error;
     async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Self::Err>;
+
+    async fn request_stream(&self, req: Request<Body>) -> Result<Response<Body>, Self::Err> {
+        let (parts, body) = req.into_parts();
+        let req = Request::from_parts(parts, body.into_vec().await?);
+        let (parts, body) = self.request(req).await?.into_parts();
+        Ok(Response::from_parts(parts, body.into()))
+    }
+
     fn helper(&self) -> &Helper;
 }
 
+mod auth;
+mod body;
+mod headers;
 mod helper;
+mod mime_match;
+mod options;
+#[cfg(any(feature = "serde-base", feature = "serde-full"))]
+mod query;
 mod response;
+mod status;
+mod timeout;
+mod uri;
@@ -0,0 +1,103 @@
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+
+/// Options of a `http_service` method.
+///
+/// The generated code attaches them to the extensions of every `Request`,
+/// so that middlewares can adjust their behavior per method.
+///
+/// Fields are private so that new options don't break code constructing it,
+/// construct it by `new` and setters.
+///
+/// ```rust
+/// use interfacer_http::RequestOptions;
+/// use interfacer_http::http::Request;
+///
+/// let mut req = Request::new(Vec::<u8>::new());
+/// req.extensions_mut().insert(RequestOptions::new().with_retry(true));
+/// assert!(RequestOptions::of(&req).retry());
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct RequestOptions {
+    retry: bool,
+    compress: Option<ContentEncoding>,
+}
+
+impl RequestOptions {
+    /// Construct default options.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// options attached to a request, or default options.
+    pub fn of<T>(req: &crate::http::Request<T>) -> Self {
+        req.extensions().get::<Self>().cloned().unwrap_or_default()
+    }
+
+    /// set whether the method can be retried even if it is not idempotent.
+    pub fn with_retry(self, retry: bool) -> Self {
+        Self { retry, ..self }
+    }
+
+    /// set encoding to compress the request body.
+    pub fn with_compress(self, compress: Option<ContentEncoding>) -> Self {
+        Self { compress, ..self }
+    }
+
+    /// the method can be retried even if it is not idempotent, set by `#[retry]`.
+    pub fn retry(&self) -> bool {
+        self.retry
+    }
+
+    /// encoding to compress the request body, set by `#[compress]`.
+    pub fn compress(&self) -> Option<ContentEncoding> {
+        self.compress
+    }
+}
+
+/// Content coding of `Content-Encoding` and `Accept-Encoding`.
+///
+/// ```rust
+/// use interfacer_http::ContentEncoding;
+///
+/// assert_eq!(ContentEncoding::Brotli, "br".parse().unwrap());
+/// assert_eq!("gzip", ContentEncoding::Gzip.as_str());
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum ContentEncoding {
+    /// `gzip`, also known as `x-gzip`.
+    Gzip,
+    /// `deflate`, zlib format.
+    Deflate,
+    /// `br`
+    Brotli,
+}
+
+impl ContentEncoding {
+    /// token in headers.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContentEncoding::Gzip => "gzip",
+            ContentEncoding::Deflate => "deflate",
+            ContentEncoding::Brotli => "br",
+        }
+    }
+}
+
+impl Display for ContentEncoding {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for ContentEncoding {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Ok(ContentEncoding::Gzip),
+            "deflate" => Ok(ContentEncoding::Deflate),
+            "br" => Ok(ContentEncoding::Brotli),
+            _ => Err(format!("unsupported content encoding: {}", s)),
+        }
+    }
+}
@@ -0,0 +1,546 @@
+use crate::mime::APPLICATION_WWW_FORM_URLENCODED;
+use crate::url::Url;
+use crate::ToContentError;
+use derive_more::Display;
+use serde::ser::{self, Impossible, Serialize};
+
+/// serialize a value into query pairs and append them to the query of `url`.
+///
+/// - a struct or map is flattened, each field becomes a pair.
+/// - any other value becomes a single pair named `name`.
+/// - `None` is skipped, sequences become repeated pairs with the same name.
+///
+/// ```rust
+/// use interfacer_http::{append_query, url::Url};
+/// let mut url: Url = "https://mock.rs/api/user?sort=name".parse().unwrap();
+/// append_query(&mut url, "tag", &vec!["a", "b"]).unwrap();
+/// append_query(&mut url, "age_max", &None::<u8>).unwrap();
+/// assert_eq!("https://mock.rs/api/user?sort=name&tag=a&tag=b", url.as_str());
+/// ```
+pub fn append_query(
+    url: &mut Url,
+    name: &str,
+    value: &(impl Serialize + ?Sized),
+) -> Result<(), ToContentError> {
+    let mut pairs = Vec::new();
+    value.serialize(TopLevel {
+        name,
+        pairs: &mut pairs,
+    })?;
+    if !pairs.is_empty() {
+        url.query_pairs_mut().extend_pairs(pairs);
+    }
+    Ok(())
+}
+
+/// Error for query serialization.
+#[derive(Debug, Display)]
+#[display(fmt = "{}", _0)]
+struct Error(String);
+
+impl std::error::Error for Error {}
+
+impl ser::Error for Error {
+    fn custom<T: std::fmt::Display>(msg: T) -> Self {
+        Error(msg.to_string())
+    }
+}
+
+impl From<Error> for ToContentError {
+    fn from(err: Error) -> Self {
+        (APPLICATION_WWW_FORM_URLENCODED, err.0).into()
+    }
+}
+
+type Pairs = Vec<(String, String)>;
+
+fn unsupported<T>(typ: &str) -> Result<T, Error> {
+    Err(Error(format!("unsupported query type: {}", typ)))
+}
+
+/// Serializer for the whole value of a `#[query]` parameter.
+struct TopLevel<'a> {
+    name: &'a str,
+    pairs: &'a mut Pairs,
+}
+
+impl<'a> TopLevel<'a> {
+    fn value(self) -> Value<'a> {
+        Value {
+            key: self.name.to_owned(),
+            pairs: self.pairs,
+        }
+    }
+}
+
+macro_rules! forward_to_value {
+    ($($method:ident($($arg:ident: $typ:ty),*);)*) => {
+        $(
+            fn $method(self, $($arg: $typ),*) -> Result<(), Error> {
+                self.value().$method($($arg),*)
+            }
+        )*
+    };
+}
+
+impl<'a> ser::Serializer for TopLevel<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Value<'a>;
+    type SerializeTuple = Value<'a>;
+    type SerializeTupleStruct = Value<'a>;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Fields<'a>;
+    type SerializeStruct = Fields<'a>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    forward_to_value! {
+        serialize_bool(v: bool);
+        serialize_i8(v: i8);
+        serialize_i16(v: i16);
+        serialize_i32(v: i32);
+        serialize_i64(v: i64);
+        serialize_u8(v: u8);
+        serialize_u16(v: u16);
+        serialize_u32(v: u32);
+        serialize_u64(v: u64);
+        serialize_f32(v: f32);
+        serialize_f64(v: f64);
+        serialize_char(v: char);
+        serialize_str(v: &str);
+        serialize_bytes(v: &[u8]);
+        serialize_unit();
+        serialize_unit_struct(name: &'static str);
+        serialize_unit_variant(name: &'static str, index: u32, variant: &'static str);
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        unsupported("newtype variant")
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Value<'a>, Error> {
+        self.value().serialize_seq(len)
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Value<'a>, Error> {
+        self.value().serialize_tuple(len)
+    }
+
+    fn serialize_tuple_struct(self, name: &'static str, len: usize) -> Result<Value<'a>, Error> {
+        self.value().serialize_tuple_struct(name, len)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("tuple variant")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Fields<'a>, Error> {
+        Ok(Fields {
+            key: None,
+            pairs: self.pairs,
+        })
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<Fields<'a>, Error> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("struct variant")
+    }
+}
+
+/// Serializer for fields of a struct or map.
+struct Fields<'a> {
+    key: Option<String>,
+    pairs: &'a mut Pairs,
+}
+
+impl<'a> Fields<'a> {
+    fn field<T: ?Sized + Serialize>(&mut self, key: String, value: &T) -> Result<(), Error> {
+        value.serialize(Value {
+            key,
+            pairs: self.pairs,
+        })
+    }
+}
+
+impl<'a> ser::SerializeStruct for Fields<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.field(key.to_owned(), value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeMap for Fields<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_key<T: ?Sized + Serialize>(&mut self, key: &T) -> Result<(), Error> {
+        let mut pairs = Vec::new();
+        key.serialize(Value {
+            key: String::new(),
+            pairs: &mut pairs,
+        })?;
+        match pairs.pop() {
+            Some((_, key)) if pairs.is_empty() => {
+                self.key = Some(key);
+                Ok(())
+            }
+            _ => unsupported("map key"),
+        }
+    }
+
+    fn serialize_value<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        match self.key.take() {
+            Some(key) => self.field(key, value),
+            None => Err(Error("map value serialized before key".to_owned())),
+        }
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+/// Serializer for a value of pairs named `key`.
+struct Value<'a> {
+    key: String,
+    pairs: &'a mut Pairs,
+}
+
+impl<'a> Value<'a> {
+    fn push(self, value: impl ToString) -> Result<(), Error> {
+        self.pairs.push((self.key, value.to_string()));
+        Ok(())
+    }
+
+    fn element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(Value {
+            key: self.key.clone(),
+            pairs: self.pairs,
+        })
+    }
+}
+
+impl<'a> ser::Serializer for Value<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Impossible<(), Error>;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<(), Error> {
+        self.push(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+        match std::str::from_utf8(v) {
+            Ok(value) => self.push(value),
+            Err(_) => unsupported("non-utf8 bytes"),
+        }
+    }
+
+    fn serialize_none(self) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, value: &T) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<(), Error> {
+        self.push("")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+        self.push("")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        variant: &'static str,
+    ) -> Result<(), Error> {
+        self.push(variant)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        unsupported("newtype variant")
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("tuple variant")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("nested map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        unsupported("nested struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("struct variant")
+    }
+}
+
+impl<'a> ser::SerializeSeq for Value<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTuple for Value<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'a> ser::SerializeTupleStruct for Value<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        self.element(value)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use super::{append_query, Url};
+    use crate::ToContentError;
+    use serde_derive::Serialize;
+    use std::collections::BTreeMap;
+
+    #[derive(Serialize)]
+    struct Filter<'a> {
+        age_max: Option<u8>,
+        age_min: Option<u8>,
+        tags: Vec<&'a str>,
+        name: &'a str,
+    }
+
+    #[derive(Serialize)]
+    struct Nested {
+        filter: Filter<'static>,
+    }
+
+    fn query(value: &(impl serde::Serialize + ?Sized)) -> Result<Option<String>, ToContentError> {
+        let mut url: Url = "https://mock.rs/api/user".parse().unwrap();
+        append_query(&mut url, "value", value)?;
+        Ok(url.query().map(ToOwned::to_owned))
+    }
+
+    #[test]
+    fn single_value() -> Result<(), ToContentError> {
+        assert_eq!(Some("value=40".to_owned()), query(&40u8)?);
+        assert_eq!(Some("value=a+b%26c".to_owned()), query("a b&c")?);
+        assert_eq!(Some("value=true".to_owned()), query(&Some(true))?);
+        assert_eq!(None, query(&None::<u8>)?);
+        assert_eq!(Some("value=1&value=2".to_owned()), query(&[1, 2])?);
+        assert_eq!(None, query(&Vec::<u8>::new())?);
+        Ok(())
+    }
+
+    #[test]
+    fn struct_value() -> Result<(), ToContentError> {
+        assert_eq!(
+            Some("age_max=40&tags=a&tags=b&name=hexi".to_owned()),
+            query(&Filter {
+                age_max: Some(40),
+                age_min: None,
+                tags: vec!["a", "b"],
+                name: "hexi",
+            })?
+        );
+        let mut map = BTreeMap::new();
+        map.insert(1, "a");
+        map.insert(2, "b");
+        assert_eq!(Some("1=a&2=b".to_owned()), query(&map)?);
+        Ok(())
+    }
+
+    #[test]
+    fn merge_with_template() -> Result<(), ToContentError> {
+        let mut url: Url = "https://mock.rs/api/user?sort=name".parse().unwrap();
+        append_query(&mut url, "age_max", &Some(40))?;
+        append_query(&mut url, "age_min", &None::<u8>)?;
+        assert_eq!(
+            "https://mock.rs/api/user?sort=name&age_max=40",
+            url.as_str()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn nested_struct() {
+        assert!(query(&Nested {
+            filter: Filter {
+                age_max: None,
+                age_min: None,
+                tags: Vec::new(),
+                name: "",
+            },
+        })
+        .is_err());
+    }
+}
@@ -0,0 +1,98 @@
+use crate::http::StatusCode;
+use std::fmt::{self, Display, Formatter};
+use std::ops::RangeInclusive;
+
+/// Set of expected status codes, constructed by `#[expect]`.
+///
+/// ```rust
+/// use interfacer_http::StatusSet;
+/// use interfacer_http::http::StatusCode;
+///
+/// let set = StatusSet::new(vec![200..=200, 204..=204]);
+/// assert!(set.contains(StatusCode::NO_CONTENT));
+/// assert!(!set.contains(StatusCode::CREATED));
+/// assert_eq!("200 | 204", &set.to_string());
+/// assert_eq!("2xx", &StatusSet::success().to_string());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct StatusSet {
+    ranges: Vec<RangeInclusive<u16>>,
+}
+
+impl StatusSet {
+    /// Construct a set from inclusive ranges.
+    pub fn new(ranges: impl IntoIterator<Item = RangeInclusive<u16>>) -> Self {
+        Self {
+            ranges: ranges.into_iter().collect(),
+        }
+    }
+
+    /// 2xx.
+    pub fn success() -> Self {
+        Self::new(Some(200..=299))
+    }
+
+    /// whether `status` is expected.
+    pub fn contains(&self, status: StatusCode) -> bool {
+        let code = status.as_u16();
+        self.ranges.iter().any(|range| range.contains(&code))
+    }
+
+    /// inclusive ranges of the set.
+    pub fn ranges(&self) -> &[RangeInclusive<u16>] {
+        &self.ranges
+    }
+}
+
+impl From<StatusCode> for StatusSet {
+    fn from(status: StatusCode) -> Self {
+        let code = status.as_u16();
+        Self::new(Some(code..=code))
+    }
+}
+
+impl Display for StatusSet {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        for (index, range) in self.ranges.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" | ")?;
+            }
+            let (start, end) = (*range.start(), *range.end());
+            if start == end {
+                write!(f, "{}", start)?;
+            } else if start % 100 == 0 && end == start + 99 {
+                write!(f, "{}xx", start / 100)?;
+            } else {
+                write!(f, "{}..={}", start, end)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StatusSet;
+    use crate::http::StatusCode;
+
+    #[test]
+    fn contains() {
+        let set = StatusSet::new(vec![200..=200, 300..=399]);
+        assert!(set.contains(StatusCode::OK));
+        assert!(set.contains(StatusCode::NOT_MODIFIED));
+        assert!(!set.contains(StatusCode::CREATED));
+        assert!(!set.contains(StatusCode::NOT_FOUND));
+        assert!(StatusSet::success().contains(StatusCode::ACCEPTED));
+        assert!(StatusSet::from(StatusCode::CREATED).contains(StatusCode::CREATED));
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!("200", &StatusSet::from(StatusCode::OK).to_string());
+        assert_eq!("2xx", &StatusSet::success().to_string());
+        assert_eq!(
+            "200 | 3xx | 400..=404",
+            &StatusSet::new(vec![200..=200, 300..=399, 400..=404]).to_string()
+        );
+    }
+}
@@ -0,0 +1,66 @@
+use crate::Timeout;
+use futures::future::{self, Either, Future};
+use futures::pin_mut;
+use futures_timer::Delay;
+use std::time::Duration;
+
+/// await `fut` within `duration`, or fail with `Timeout`.
+///
+/// `fut` is awaited directly if `duration` is None.
+///
+/// ```rust
+/// use interfacer_http::{timeout, Timeout};
+/// use futures::{executor::block_on, future};
+/// use std::time::Duration;
+///
+/// let fut = future::pending::<Result<(), Timeout>>();
+/// let err = block_on(timeout(Some(Duration::from_millis(1)), fut)).unwrap_err();
+/// assert_eq!(Duration::from_millis(1), err.duration());
+/// ```
+pub async fn timeout<F, T, E>(duration: Option<Duration>, fut: F) -> Result<T, E>
+where
+    F: Future<Output = Result<T, E>>,
+    E: From<Timeout>,
+{
+    let duration = match duration {
+        Some(duration) => duration,
+        None => return fut.await,
+    };
+    let delay = Delay::new(duration);
+    pin_mut!(fut);
+    match future::select(fut, delay).await {
+        Either::Left((result, _)) => result,
+        Either::Right(_) => Err(Timeout::new(duration).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::timeout;
+    use crate::Timeout;
+    use futures::executor::block_on;
+    use futures::future;
+    use futures_timer::Delay;
+    use std::time::Duration;
+
+    #[test]
+    fn ready() -> Result<(), Timeout> {
+        let fut = future::ready(Ok::<_, Timeout>(1));
+        assert_eq!(1, block_on(timeout(Some(Duration::from_secs(1)), fut))?);
+        assert_eq!(
+            2,
+            block_on(timeout(None, future::ready(Ok::<_, Timeout>(2))))?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn elapsed() {
+        let fut = async {
+            Delay::new(Duration::from_secs(10)).await;
+            Ok::<_, Timeout>(())
+        };
+        let err = block_on(timeout(Some(Duration::from_millis(10)), fut)).unwrap_err();
+        assert_eq!(Duration::from_millis(10), err.duration());
+    }
+}
@@ -0,0 +1,102 @@
+use crate::PathSegmentError;
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use std::fmt::Display;
+
+/// Characters to be escaped in a path segment.
+///
+/// Everything except `pchar` defined in RFC 3986 section 3.3.
+const PATH_SEGMENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'&')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b'+')
+    .remove(b',')
+    .remove(b';')
+    .remove(b'=')
+    .remove(b':')
+    .remove(b'@');
+
+/// Characters to be escaped in a value of query.
+///
+/// Everything except `query` characters defined in RFC 3986 section 3.4,
+/// excluding the delimiters `&`, `=` and `+`.
+const QUERY_COMPONENT: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'.')
+    .remove(b'_')
+    .remove(b'~')
+    .remove(b'!')
+    .remove(b'$')
+    .remove(b'\'')
+    .remove(b'(')
+    .remove(b')')
+    .remove(b'*')
+    .remove(b',')
+    .remove(b';')
+    .remove(b':')
+    .remove(b'@')
+    .remove(b'/')
+    .remove(b'?');
+
+/// percent-encode a value as a single path segment.
+///
+/// `.` and `..` are rejected, as they are dot-segments even if escaped.
+///
+/// ```rust
+/// use interfacer_http::encode_path_segment;
+/// assert_eq!("a%2Fb%20c", encode_path_segment(&"a/b c").unwrap());
+/// assert_eq!("..%2Fadmin", encode_path_segment(&"../admin").unwrap());
+/// assert!(encode_path_segment(&"..").is_err());
+/// ```
+pub fn encode_path_segment(value: &(impl Display + ?Sized)) -> Result<String, PathSegmentError> {
+    let value = value.to_string();
+    match value.as_str() {
+        "." | ".." => Err(PathSegmentError::new(value)),
+        _ => Ok(utf8_percent_encode(&value, PATH_SEGMENT).to_string()),
+    }
+}
+
+/// percent-encode a value as a component of query.
+///
+/// ```rust
+/// use interfacer_http::encode_query_component;
+/// assert_eq!("a%26b%3Dc%23", encode_query_component(&"a&b=c#"));
+/// ```
+pub fn encode_query_component(value: &(impl Display + ?Sized)) -> String {
+    utf8_percent_encode(&value.to_string(), QUERY_COMPONENT).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{encode_path_segment, encode_query_component};
+
+    #[test]
+    fn path_segment() {
+        assert_eq!("0", encode_path_segment(&0).unwrap());
+        assert_eq!("hexi", encode_path_segment(&"hexi").unwrap());
+        assert_eq!("a%2Fb%20c", encode_path_segment(&"a/b c").unwrap());
+        assert_eq!("%3Fq%23f", encode_path_segment(&"?q#f").unwrap());
+        assert_eq!("%25", encode_path_segment(&"%").unwrap());
+        assert_eq!("%E4%BD%A0", encode_path_segment(&"你").unwrap());
+        assert_eq!("...", encode_path_segment(&"...").unwrap());
+        assert_eq!("user@host:1", encode_path_segment(&"user@host:1").unwrap());
+        assert!(encode_path_segment(&".").is_err());
+        assert!(encode_path_segment(&"..").is_err());
+    }
+
+    #[test]
+    fn query_component() {
+        assert_eq!("40", encode_query_component(&40));
+        assert_eq!("a%2Bb%26c%3Dd", encode_query_component(&"a+b&c=d"));
+        assert_eq!("/path?x", encode_query_component(&"/path?x"));
+        assert_eq!("%20%23", encode_query_component(&" #"));
+    }
+}
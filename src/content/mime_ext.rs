@@ -1,8 +1,12 @@
-use crate::mime::Mime;
+use crate::mime::{self, Mime};
 
 /// Extensional trait for `mime::Mime`.
 pub trait MimeExt {
     fn pure_type(&self) -> Self;
+
+    /// self with parameters of `actual`, like charset,
+    /// or `actual` if self is a wildcard like `application/*`.
+    fn with_params_of(&self, actual: &Self) -> Self;
 }
 
 impl MimeExt for Mime {
@@ -14,11 +18,46 @@ impl MimeExt for Mime {
         }
         ret.parse().unwrap()
     }
+
+    fn with_params_of(&self, actual: &Self) -> Self {
+        if self.type_() == mime::STAR || self.subtype() == mime::STAR {
+            return actual.clone();
+        }
+        let mut ret = self.pure_type().to_string();
+        for (name, value) in actual.params() {
+            ret += &format!("; {}={}", name, value);
+        }
+        ret.parse().unwrap_or_else(|_| self.clone())
+    }
+}
+
+/// format `Accept` header of acceptable types in order of preference.
+///
+/// quality values decrease from 1 by 0.1, and 0.1 at least.
+///
+/// ```rust
+/// use interfacer_http::{format_accept, mime};
+///
+/// assert_eq!(
+///     "application/json, application/msgpack;q=0.9",
+///     format_accept(&[mime::APPLICATION_JSON, mime::APPLICATION_MSGPACK])
+/// );
+/// ```
+pub fn format_accept(types: &[Mime]) -> String {
+    types
+        .iter()
+        .enumerate()
+        .map(|(index, mime)| match index {
+            0 => mime.to_string(),
+            _ => format!("{};q=0.{}", mime, 10 - index.min(9)),
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[cfg(test)]
 mod test {
-    use super::{Mime, MimeExt};
+    use super::{format_accept, Mime, MimeExt};
     use std::str::FromStr;
     #[test]
     fn test_pure_type() {
@@ -35,4 +74,25 @@ mod test {
             &"image/svg+xml"
         );
     }
+
+    #[test]
+    fn test_with_params_of() {
+        let actual = Mime::from_str("application/problem+json; charset=utf-8").unwrap();
+        assert_eq!(
+            Mime::from_str("application/json; charset=utf-8").unwrap(),
+            mime::APPLICATION_JSON.with_params_of(&actual)
+        );
+        let wildcard = Mime::from_str("application/*").unwrap();
+        assert_eq!(actual, wildcard.with_params_of(&actual));
+    }
+
+    #[test]
+    fn test_format_accept() {
+        assert_eq!("", format_accept(&[]));
+        assert_eq!("text/html", format_accept(&[mime::TEXT_HTML]));
+        let types = vec![mime::TEXT_HTML; 12];
+        let accept = format_accept(&types);
+        assert!(accept.starts_with("text/html, text/html;q=0.9, text/html;q=0.8"));
+        assert!(accept.ends_with("text/html;q=0.1, text/html;q=0.1"));
+    }
 }
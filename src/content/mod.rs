@@ -1,12 +1,14 @@
 #[doc(hidden)]
 pub mod polyfill;
 pub use error::{FromContentError, ToContentError};
-pub use mime_ext::MimeExt;
+pub use mime_ext::{format_accept, MimeExt};
+pub use multipart::{Multipart, MultipartPart};
 
 #[cfg(feature = "encoding")]
 mod encoding;
 mod error;
 mod mime_ext;
+mod multipart;
 #[cfg(any(feature = "serde-base", feature = "serde-full"))]
 mod serde_support;
 #[cfg(feature = "unhtml-html")]
@@ -28,6 +30,11 @@ pub trait FromContent: Sized {
 /// body of `http_service` method should implement ToContent.
 pub trait ToContent {
     fn to_content(&self, content_type: &Mime) -> Result<Vec<u8>, ToContentError>;
+
+    /// `Content-Type` of request, bodies can append parameters to it, like `boundary` of multipart.
+    fn to_content_type(&self, content_type: &Mime) -> Mime {
+        content_type.clone()
+    }
 }
 
 /// Wrapped trait of `FromContent` for generic return type deduction.
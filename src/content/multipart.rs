@@ -0,0 +1,423 @@
+use super::error::{FromContentError, ToContentError};
+use crate::mime::{Mime, BOUNDARY, FORM_DATA, MULTIPART};
+use crate::{FromContent, MimeExt, ToContent};
+use rand::distributions::Alphanumeric;
+use rand::Rng;
+
+const BOUNDARY_PREFIX: &str = "interfacer-http-";
+const BOUNDARY_LEN: usize = 24;
+const CRLF: &[u8] = b"\r\n";
+
+/// Body of `multipart/form-data`.
+///
+/// The boundary is generated when constructing,
+/// it's appended to `Content-Type` of request by `ToContent::to_content_type`.
+///
+/// ```rust
+/// use interfacer_http::{mime, Multipart, ToContent};
+///
+/// let form = Multipart::new()
+///     .with_text("name", "hexi")
+///     .with_file("avatar", "avatar.png", mime::IMAGE_PNG, vec![0x89, 0x50]);
+/// let content_type = form.to_content_type(&mime::MULTIPART_FORM_DATA);
+/// assert_eq!(Some(form.boundary()), content_type.get_param(mime::BOUNDARY).map(|b| b.as_str()));
+/// let data = form.to_content(&content_type).unwrap();
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<MultipartPart>,
+}
+
+/// Part of `Multipart`, a text field or a file.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct MultipartPart {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    data: Vec<u8>,
+}
+
+impl Multipart {
+    /// Construct an empty form with a random boundary.
+    pub fn new() -> Self {
+        let random: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(BOUNDARY_LEN)
+            .collect();
+        Self {
+            boundary: format!("{}{}", BOUNDARY_PREFIX, random),
+            parts: Vec::new(),
+        }
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Multipart {
+    /// set boundary.
+    pub fn with_boundary(self, boundary: impl Into<String>) -> Self {
+        Self {
+            boundary: boundary.into(),
+            ..self
+        }
+    }
+
+    /// append a part.
+    pub fn with_part(mut self, part: MultipartPart) -> Self {
+        self.parts.push(part);
+        self
+    }
+
+    /// append a text field.
+    pub fn with_text(self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.with_part(MultipartPart::text(name, value))
+    }
+
+    /// append a file.
+    pub fn with_file(
+        self,
+        name: impl Into<String>,
+        filename: impl Into<String>,
+        content_type: Mime,
+        data: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.with_part(
+            MultipartPart::bytes(name, data)
+                .with_filename(filename)
+                .with_content_type(content_type),
+        )
+    }
+
+    /// boundary of parts.
+    pub fn boundary(&self) -> &str {
+        &self.boundary
+    }
+
+    /// all parts in order.
+    pub fn parts(&self) -> &[MultipartPart] {
+        &self.parts
+    }
+
+    /// the first part named `name`.
+    pub fn part(&self, name: &str) -> Option<&MultipartPart> {
+        self.parts.iter().find(|part| part.name == name)
+    }
+}
+
+impl MultipartPart {
+    /// Construct a text field.
+    pub fn text(name: impl Into<String>, value: impl Into<String>) -> Self {
+        Self::bytes(name, value.into().into_bytes())
+    }
+
+    /// Construct a binary part.
+    pub fn bytes(name: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            data: data.into(),
+        }
+    }
+
+    /// set filename.
+    pub fn with_filename(self, filename: impl Into<String>) -> Self {
+        Self {
+            filename: Some(filename.into()),
+            ..self
+        }
+    }
+
+    /// set `Content-Type` of this part.
+    pub fn with_content_type(self, content_type: Mime) -> Self {
+        Self {
+            content_type: Some(content_type),
+            ..self
+        }
+    }
+
+    /// name of field.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// filename, None if it's not a file.
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// `Content-Type` of this part.
+    pub fn content_type(&self) -> Option<&Mime> {
+        self.content_type.as_ref()
+    }
+
+    /// raw data.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// data as text, None if it's not utf-8.
+    pub fn as_text(&self) -> Option<&str> {
+        std::str::from_utf8(&self.data).ok()
+    }
+}
+
+fn is_form_data(content_type: &Mime) -> bool {
+    content_type.type_() == MULTIPART && content_type.subtype() == FORM_DATA
+}
+
+impl ToContent for Multipart {
+    fn to_content(&self, content_type: &Mime) -> Result<Vec<u8>, ToContentError> {
+        if !is_form_data(content_type) {
+            return Err(content_type.pure_type().into());
+        }
+        let boundary = match content_type.get_param(BOUNDARY) {
+            Some(boundary) => boundary.as_str(),
+            None => self.boundary.as_str(),
+        };
+        let mut data = Vec::new();
+        for part in self.parts.iter() {
+            data.extend_from_slice(format!("--{}\r\n", boundary).as_bytes());
+            let mut disposition = format!("form-data; name=\"{}\"", escape(&part.name));
+            if let Some(filename) = &part.filename {
+                disposition += &format!("; filename=\"{}\"", escape(filename));
+            }
+            data.extend_from_slice(format!("Content-Disposition: {}\r\n", disposition).as_bytes());
+            if let Some(content_type) = &part.content_type {
+                data.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+            }
+            data.extend_from_slice(CRLF);
+            data.extend_from_slice(&part.data);
+            data.extend_from_slice(CRLF);
+        }
+        data.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+        Ok(data)
+    }
+
+    fn to_content_type(&self, content_type: &Mime) -> Mime {
+        if !is_form_data(content_type) || content_type.get_param(BOUNDARY).is_some() {
+            return content_type.clone();
+        }
+        format!("{}; boundary={}", content_type, self.boundary)
+            .parse()
+            .unwrap_or_else(|_| content_type.clone())
+    }
+}
+
+impl FromContent for Multipart {
+    fn from_content(data: Vec<u8>, content_type: &Mime) -> Result<Self, FromContentError> {
+        if !is_form_data(content_type) {
+            return Err(content_type.pure_type().into());
+        }
+        let boundary = match content_type.get_param(BOUNDARY) {
+            Some(boundary) => boundary.as_str().to_owned(),
+            None => return Err(invalid(data, content_type, "boundary not found")),
+        };
+        match parse_parts(&data, &boundary) {
+            Ok(parts) => Ok(Self { boundary, parts }),
+            Err(msg) => Err(invalid(data, content_type, msg)),
+        }
+    }
+}
+
+fn invalid(data: Vec<u8>, content_type: &Mime, msg: &str) -> FromContentError {
+    (data, content_type.pure_type(), msg.to_owned()).into()
+}
+
+/// escape quoted-string in `Content-Disposition`, as browsers do.
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+fn unescape(value: &str) -> String {
+    value
+        .replace("%22", "\"")
+        .replace("%0D", "\r")
+        .replace("%0A", "\n")
+}
+
+fn find(data: &[u8], pattern: &[u8]) -> Option<usize> {
+    data.windows(pattern.len())
+        .position(|window| window == pattern)
+}
+
+fn parse_parts(data: &[u8], boundary: &str) -> Result<Vec<MultipartPart>, &'static str> {
+    let delimiter = format!("--{}", boundary).into_bytes();
+    let body_delimiter = [CRLF, &delimiter].concat();
+    let mut rest = match find(data, &delimiter) {
+        Some(start) => &data[start + delimiter.len()..],
+        None => return Err("delimiter not found"),
+    };
+    let mut parts = Vec::new();
+    loop {
+        if rest.starts_with(b"--") {
+            return Ok(parts);
+        }
+        if !rest.starts_with(CRLF) {
+            return Err("delimiter should be followed by CRLF");
+        }
+        rest = &rest[CRLF.len()..];
+        let header_end = find(rest, b"\r\n\r\n").ok_or("headers of part are not terminated")?;
+        let headers = std::str::from_utf8(&rest[..header_end])
+            .map_err(|_| "headers of part are not utf-8")?;
+        rest = &rest[header_end + 4..];
+        let body_end = find(rest, &body_delimiter).ok_or("part is not terminated")?;
+        parts.push(parse_part(headers, rest[..body_end].to_vec())?);
+        rest = &rest[body_end + body_delimiter.len()..];
+    }
+}
+
+fn parse_part(headers: &str, data: Vec<u8>) -> Result<MultipartPart, &'static str> {
+    let mut name = None;
+    let mut filename = None;
+    let mut content_type = None;
+    for line in headers.split("\r\n") {
+        let (key, value) = match line.find(':') {
+            Some(index) => (line[..index].trim(), line[index + 1..].trim()),
+            None => return Err("invalid header of part"),
+        };
+        if key.eq_ignore_ascii_case("content-disposition") {
+            for (param, value) in disposition_params(value) {
+                if param.eq_ignore_ascii_case("name") {
+                    name = Some(value)
+                } else if param.eq_ignore_ascii_case("filename") {
+                    filename = Some(value)
+                }
+            }
+        } else if key.eq_ignore_ascii_case("content-type") {
+            content_type = Some(value.parse().map_err(|_| "invalid content type of part")?);
+        }
+    }
+    Ok(MultipartPart {
+        name: name.ok_or("name of part not found")?,
+        filename,
+        content_type,
+        data,
+    })
+}
+
+/// parameters of `Content-Disposition`, semicolons in quoted-string are allowed.
+fn disposition_params(value: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for c in value.chars() {
+        match c {
+            '"' => {
+                quoted = !quoted;
+                current.push(c)
+            }
+            ';' if !quoted => segments.push(std::mem::take(&mut current)),
+            _ => current.push(c),
+        }
+    }
+    segments.push(current);
+    for segment in segments.iter() {
+        if let Some(index) = segment.find('=') {
+            let key = segment[..index].trim().to_owned();
+            let value = segment[index + 1..].trim().trim_matches('"');
+            params.push((key, unescape(value)));
+        }
+    }
+    params
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Multipart, MultipartPart};
+    use crate::mime::{self, Mime, BOUNDARY};
+    use crate::{ContentInto, FromContentError, ToContent, ToContentError};
+
+    fn form() -> Multipart {
+        Multipart::new()
+            .with_text("name", "hexi")
+            .with_text("quote", "a \"quoted\"; value")
+            .with_file(
+                "avatar",
+                "avatar.png",
+                mime::IMAGE_PNG,
+                vec![0x89, 0x50, 0x0d, 0x0a],
+            )
+    }
+
+    #[test]
+    fn content_type() {
+        let form = form();
+        let content_type = form.to_content_type(&mime::MULTIPART_FORM_DATA);
+        assert_eq!(
+            form.boundary(),
+            content_type.get_param(BOUNDARY).unwrap().as_str()
+        );
+        assert_eq!(
+            mime::APPLICATION_JSON,
+            form.to_content_type(&mime::APPLICATION_JSON)
+        );
+    }
+
+    #[test]
+    fn to_content() -> Result<(), ToContentError> {
+        let form = Multipart::new()
+            .with_boundary("boundary")
+            .with_text("name", "hexi")
+            .with_file("file", "a.txt", mime::TEXT_PLAIN, b"hello".to_vec());
+        let content_type = form.to_content_type(&mime::MULTIPART_FORM_DATA);
+        assert_eq!(
+            "--boundary\r\n\
+             Content-Disposition: form-data; name=\"name\"\r\n\
+             \r\n\
+             hexi\r\n\
+             --boundary\r\n\
+             Content-Disposition: form-data; name=\"file\"; filename=\"a.txt\"\r\n\
+             Content-Type: text/plain\r\n\
+             \r\n\
+             hello\r\n\
+             --boundary--\r\n",
+            String::from_utf8(form.to_content(&content_type)?).unwrap()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), FromContentError> {
+        let form = form();
+        let content_type = form.to_content_type(&mime::MULTIPART_FORM_DATA);
+        let data = form.to_content(&content_type).unwrap();
+        let parsed: Multipart = data.content_into(&content_type)?;
+        assert_eq!(form, parsed);
+        assert_eq!(
+            Some("hexi"),
+            parsed.part("name").and_then(MultipartPart::as_text)
+        );
+        assert_eq!(
+            Some("a \"quoted\"; value"),
+            parsed.part("quote").and_then(MultipartPart::as_text)
+        );
+        let avatar = parsed.part("avatar").unwrap();
+        assert_eq!(Some("avatar.png"), avatar.filename());
+        assert_eq!(Some(&mime::IMAGE_PNG), avatar.content_type());
+        assert_eq!(&[0x89, 0x50, 0x0d, 0x0a], avatar.data());
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported() {
+        assert!(form().to_content(&mime::APPLICATION_JSON).is_err());
+        let no_boundary: Result<Multipart, _> = Vec::new().content_into(&mime::MULTIPART_FORM_DATA);
+        assert!(no_boundary.is_err());
+        let content_type: Mime = "multipart/form-data; boundary=b".parse().unwrap();
+        let broken: Result<Multipart, _> =
+            b"--b\r\nContent-Disposition: form-data; name=\"a\"\r\n\r\nvalue"
+                .to_vec()
+                .content_into(&content_type);
+        assert!(broken.is_err());
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
-    http::{self, header::HeaderName, Response, StatusCode},
-    url, FromContentError, ToContentError,
+    http::{self, header::HeaderName, Response},
+    mime::Mime,
+    url, AuthScheme, FromContent, FromContentError, StatusSet, ToContentError,
 };
 use derive_more::{Constructor, Display, From};
-use std::fmt::{Debug, Display};
+use std::any::Any;
+use std::fmt::{self, Debug, Display, Formatter};
+use std::io;
+use std::time::Duration;
 
 /// Error trait to constrain `HttpClient::Err`.
 pub trait Error = From<url::ParseError>
@@ -11,22 +15,164 @@ pub trait Error = From<url::ParseError>
     + From<ToContentError>
     + From<FromContentError>
     + From<Unexpected>
+    + From<PathSegmentError>
+    + From<io::Error>
+    + From<Timeout>
+    + From<AuthError>
     + Display
     + Debug;
 
 /// Error for `Response` assertion.
-#[derive(Debug, Display, Constructor)]
+///
+/// It holds the raw response, and the error body decoded by `#[error_body]`,
+/// which is a `Result<T, FromContentError>` of the declared error type `T`.
+///
+/// ### Error body
+///
+/// `#[error_body(ApiError, mime::APPLICATION_JSON)]` on a `http_service` trait or method
+/// decodes bodies of responses with unexpected status codes as `ApiError`.
+///
+/// `Self::Error` of the service only requires `From<Unexpected>`, so the error body is stored
+/// without its type, and it must be taken by the declared type:
+///
+/// ```rust,ignore
+/// match service.get_admin(0).await {
+///     Err(Error::Unexpected(err)) => match err.into_body::<ApiError>()? {
+///         Ok(api_error) => println!("{}", api_error.message),
+///         Err(decode_error) => println!("malformed error body: {}", decode_error),
+///     },
+///     other => (),
+/// }
+/// ```
+///
+/// Taking it by another type fails by `BodyTypeMismatch`.
+#[derive(Display)]
 #[display(fmt = "Unexpected: {}", typ)]
 pub struct Unexpected {
     typ: UnexpectedType,
     resp: Response<Vec<u8>>,
+    body: Option<Box<dyn Any + Send + Sync>>,
+    body_type: Option<&'static str>,
+}
+
+impl Unexpected {
+    /// Construct an Unexpected without error body.
+    pub fn new(typ: UnexpectedType, resp: Response<Vec<u8>>) -> Self {
+        Self {
+            typ,
+            resp,
+            body: None,
+            body_type: None,
+        }
+    }
+
+    /// Construct an Unexpected with an error body decoded from `resp` as `content_type`.
+    ///
+    /// The error body is decoded from a copy of the response body,
+    /// the raw response is kept unchanged.
+    pub fn decode<T>(typ: UnexpectedType, resp: Response<Vec<u8>>, content_type: &Mime) -> Self
+    where
+        T: FromContent + Any + Send + Sync,
+    {
+        let decoded = T::from_content(resp.body().clone(), content_type);
+        Self::new(typ, resp).with_body(decoded)
+    }
+
+    /// bind an error body, or the error of decoding it.
+    pub fn with_body<T: Any + Send + Sync>(self, body: Result<T, FromContentError>) -> Self {
+        Self {
+            body: Some(Box::new(body)),
+            body_type: Some(std::any::type_name::<T>()),
+            ..self
+        }
+    }
+
+    /// type of assertion failure.
+    pub fn typ(&self) -> &UnexpectedType {
+        &self.typ
+    }
+
+    /// the raw response.
+    pub fn response(&self) -> &Response<Vec<u8>> {
+        &self.resp
+    }
+
+    /// unwrap into the raw response.
+    pub fn into_response(self) -> Response<Vec<u8>> {
+        self.resp
+    }
+
+    /// the error body or the decode error.
+    ///
+    /// fail if no error body is declared or it's not a `T`.
+    pub fn body<T: Any>(&self) -> Result<Result<&T, &FromContentError>, BodyTypeMismatch> {
+        self.body
+            .as_ref()
+            .and_then(|body| body.downcast_ref::<Result<T, FromContentError>>())
+            .map(Result::as_ref)
+            .ok_or_else(|| self.mismatch::<T>())
+    }
+
+    /// unwrap into the error body or the decode error.
+    ///
+    /// fail if no error body is declared or it's not a `T`, use `body` to check it first.
+    pub fn into_body<T: Any>(self) -> Result<Result<T, FromContentError>, BodyTypeMismatch> {
+        let mismatch = self.mismatch::<T>();
+        match self
+            .body
+            .map(|body| body.downcast::<Result<T, FromContentError>>())
+        {
+            Some(Ok(body)) => Ok(*body),
+            _ => Err(mismatch),
+        }
+    }
+
+    fn mismatch<T>(&self) -> BodyTypeMismatch {
+        BodyTypeMismatch {
+            expected: std::any::type_name::<T>(),
+            actual: self.body_type,
+        }
+    }
+
+    /// type name of the error body declared by `#[error_body]`.
+    pub fn body_type(&self) -> Option<&'static str> {
+        self.body_type
+    }
+}
+
+impl Debug for Unexpected {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Unexpected")
+            .field("typ", &self.typ)
+            .field("resp", &self.resp)
+            .field("body_type", &self.body_type)
+            .finish()
+    }
+}
+
+/// Error for taking the error body of `Unexpected` by a type other than the declared one.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct BodyTypeMismatch {
+    /// type taken by.
+    pub expected: &'static str,
+    /// type declared by `#[error_body]`, None if no error body is declared.
+    pub actual: Option<&'static str>,
+}
+
+impl Display for BodyTypeMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.actual {
+            Some(actual) => write!(f, "error body is `{}`, not `{}`", actual, self.expected),
+            None => write!(f, "no error body is declared, expect `{}`", self.expected),
+        }
+    }
 }
 
 /// Error type for `Response` assertion.
 #[derive(Debug, Display, From)]
 pub enum UnexpectedType {
     #[display(fmt = "status code should be {}", expect)]
-    StatusCode { expect: StatusCode },
+    StatusCode { expect: StatusSet },
     #[display(fmt = "value of header '{}' is unexpected: {}", header_name, msg)]
     Header {
         header_name: HeaderName,
@@ -34,5 +180,53 @@ pub enum UnexpectedType {
     },
 }
 
+/// Error for path template variables which cannot be encoded as a segment.
+#[derive(Debug, Display, Constructor)]
+#[display(fmt = "value '{}' cannot be a path segment", value)]
+pub struct PathSegmentError {
+    value: String,
+}
+
+/// Error for requests not completed within the timeout.
+#[derive(Debug, Display, Constructor)]
+#[display(fmt = "request timed out after {:?}", duration)]
+pub struct Timeout {
+    duration: Duration,
+}
+
+impl Timeout {
+    /// the timeout elapsed.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+}
+
+/// Error for resolving credentials required by `#[auth]`.
+#[derive(Debug, Display)]
+pub enum AuthError {
+    #[display(fmt = "no credential provider is bound to helper")]
+    NoProvider,
+
+    #[display(fmt = "no credential for {:?} auth", scheme)]
+    NoCredential { scheme: AuthScheme },
+
+    #[display(fmt = "credential cannot be a header value")]
+    InvalidCredential,
+
+    #[display(fmt = "credential provider error: {}", _0)]
+    Provider(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl AuthError {
+    /// wrap an error of custom credential provider.
+    pub fn provider(err: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        AuthError::Provider(err.into())
+    }
+}
+
 impl std::error::Error for Unexpected {}
+impl std::error::Error for BodyTypeMismatch {}
 impl std::error::Error for UnexpectedType {}
+impl std::error::Error for PathSegmentError {}
+impl std::error::Error for Timeout {}
+impl std::error::Error for AuthError {}
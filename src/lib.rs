@@ -1,5 +1,6 @@
 #![feature(trait_alias, specialization)]
 
+pub extern crate bytes;
 pub extern crate cookie;
 pub extern crate http;
 pub extern crate mime;
@@ -7,15 +8,19 @@ pub extern crate url;
 
 pub use async_trait::async_trait;
 
+pub mod middleware;
 #[cfg(feature = "mock")]
 pub mod mock;
 
 #[doc(inline)]
 pub use content::{
-    polyfill, ContentInto, FromContent, FromContentError, MimeExt, ToContent, ToContentError,
+    format_accept, polyfill, ContentInto, FromContent, FromContentError, MimeExt, Multipart,
+    MultipartPart, ToContent, ToContentError,
 };
 #[doc(inline)]
-pub use error::{Error, Unexpected, UnexpectedType};
+pub use error::{
+    AuthError, BodyTypeMismatch, Error, PathSegmentError, Timeout, Unexpected, UnexpectedType,
+};
 #[doc(inline)]
 pub use interfacer_http_attribute::http_service;
 
@@ -23,7 +28,15 @@ pub use interfacer_http_attribute::http_service;
 pub use interfacer_http_attribute::{FromContent, ToContent};
 
 #[doc(inline)]
-pub use client::{CookieError, Helper, HttpClient, ResponseExt};
+pub use client::{
+    encode_path_segment, encode_query_component, replace_headers, timeout, AuthScheme, Body,
+    ContentEncoding, CookieError, Credential, CredentialProvider, Helper, HttpClient, MimeMatch,
+    RefreshingToken, RequestOptions, ResponseExt, StatusSet, Token,
+};
+
+#[doc(inline)]
+#[cfg(any(feature = "serde-base", feature = "serde-full"))]
+pub use client::append_query;
 
 mod client;
 mod content;
@@ -0,0 +1,265 @@
+use super::Middleware;
+use crate::{
+    async_trait,
+    http::{
+        header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH},
+        HeaderMap, Request, Response,
+    },
+    ContentEncoding, HttpClient, RequestOptions,
+};
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use std::io::{self, Read, Write};
+
+const BROTLI_BUFFER_SIZE: usize = 4096;
+const BROTLI_QUALITY: u32 = 5;
+const BROTLI_WINDOW: u32 = 22;
+const DEFAULT_MAX_DECODED_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Middleware compressing request bodies and decompressing response bodies.
+///
+/// `Accept-Encoding` is advertised unless the request has one,
+/// response bodies in gzip, deflate or br are decoded before `content_into`,
+/// then `Content-Encoding` and `Content-Length` are removed.
+/// Responses in unsupported encodings are returned as they are.
+/// Decoded bodies larger than the max decoded size, 64MiB by default, fail by `io::Error`.
+///
+/// Request bodies of methods annotated by `#[compress(gzip)]`, `#[compress(deflate)]`
+/// or `#[compress(br)]` are compressed, unless the request has a `Content-Encoding`.
+///
+/// ```rust
+/// use interfacer_http::middleware::Compression;
+/// use interfacer_http::ContentEncoding;
+///
+/// let compression = Compression::new()
+///     .with_encodings(vec![ContentEncoding::Gzip])
+///     .with_max_decoded_size(16 * 1024 * 1024);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Compression {
+    encodings: Vec<ContentEncoding>,
+    max_decoded_size: u64,
+}
+
+impl Compression {
+    /// Construct a default Compression.
+    ///
+    /// Accept gzip, deflate and br, decode bodies up to 64MiB.
+    pub fn new() -> Self {
+        Self {
+            encodings: vec![
+                ContentEncoding::Gzip,
+                ContentEncoding::Deflate,
+                ContentEncoding::Brotli,
+            ],
+            max_decoded_size: DEFAULT_MAX_DECODED_SIZE,
+        }
+    }
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    /// set encodings advertised by `Accept-Encoding`, in order of preference.
+    pub fn with_encodings(self, encodings: Vec<ContentEncoding>) -> Self {
+        Self { encodings, ..self }
+    }
+
+    /// set max size of decoded response bodies in bytes.
+    pub fn with_max_decoded_size(self, max_decoded_size: u64) -> Self {
+        Self {
+            max_decoded_size,
+            ..self
+        }
+    }
+
+    fn accept_encoding(&self) -> Option<HeaderValue> {
+        let encodings = self
+            .encodings
+            .iter()
+            .map(|encoding| encoding.as_str())
+            .collect::<Vec<_>>();
+        HeaderValue::from_str(&encodings.join(", ")).ok()
+    }
+}
+
+/// encodings of `Content-Encoding` in order of application,
+/// None if any of them is unsupported.
+fn content_encodings(headers: &HeaderMap) -> Option<Vec<ContentEncoding>> {
+    let mut encodings = Vec::new();
+    for value in headers.get_all(CONTENT_ENCODING) {
+        for token in value.to_str().ok()?.split(',') {
+            match token.trim() {
+                "" => (),
+                token if token.eq_ignore_ascii_case("identity") => (),
+                token => encodings.push(token.parse().ok()?),
+            }
+        }
+    }
+    Some(encodings)
+}
+
+fn encode(encoding: ContentEncoding, data: &[u8]) -> io::Result<Vec<u8>> {
+    match encoding {
+        ContentEncoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        ContentEncoding::Deflate => {
+            let mut encoder = ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(data)?;
+            encoder.finish()
+        }
+        ContentEncoding::Brotli => {
+            let mut encoder = brotli::CompressorWriter::new(
+                Vec::new(),
+                BROTLI_BUFFER_SIZE,
+                BROTLI_QUALITY,
+                BROTLI_WINDOW,
+            );
+            encoder.write_all(data)?;
+            encoder.flush()?;
+            Ok(encoder.into_inner())
+        }
+    }
+}
+
+/// decode `data`, fail if the decoded size exceeds `limit`.
+fn decode(encoding: ContentEncoding, data: &[u8], limit: u64) -> io::Result<Vec<u8>> {
+    // read one more byte to detect overflow.
+    let read = |decoder: &mut dyn Read, decoded: &mut Vec<u8>| {
+        decoder.take(limit.saturating_add(1)).read_to_end(decoded)
+    };
+    let mut decoded = Vec::new();
+    match encoding {
+        ContentEncoding::Gzip => read(&mut GzDecoder::new(data), &mut decoded)?,
+        // some servers send raw deflate rather than zlib.
+        ContentEncoding::Deflate => match read(&mut ZlibDecoder::new(data), &mut decoded) {
+            Ok(size) => size,
+            Err(_) => {
+                decoded.clear();
+                read(&mut DeflateDecoder::new(data), &mut decoded)?
+            }
+        },
+        ContentEncoding::Brotli => read(
+            &mut brotli::Decompressor::new(data, BROTLI_BUFFER_SIZE),
+            &mut decoded,
+        )?,
+    };
+    if decoded.len() as u64 > limit {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("decoded body exceeds {} bytes", limit),
+        ));
+    }
+    Ok(decoded)
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for Compression {
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        let mut req = req;
+        if !req.headers().contains_key(ACCEPT_ENCODING) {
+            if let Some(accept_encoding) = self.accept_encoding() {
+                req.headers_mut().insert(ACCEPT_ENCODING, accept_encoding);
+            }
+        }
+        if let Some(encoding) = RequestOptions::of(&req).compress() {
+            if !req.body().is_empty() && !req.headers().contains_key(CONTENT_ENCODING) {
+                *req.body_mut() = encode(encoding, req.body())?;
+                req.headers_mut().insert(
+                    CONTENT_ENCODING,
+                    HeaderValue::from_static(encoding.as_str()),
+                );
+                req.headers_mut().remove(CONTENT_LENGTH);
+            }
+        }
+        let mut resp = next.request(req).await?;
+        let encodings = match content_encodings(resp.headers()) {
+            Some(encodings) => encodings,
+            None => return Ok(resp),
+        };
+        if !resp.body().is_empty() {
+            for encoding in encodings.into_iter().rev() {
+                *resp.body_mut() = decode(encoding, resp.body(), self.max_decoded_size)?;
+            }
+        }
+        resp.headers_mut().remove(CONTENT_ENCODING);
+        resp.headers_mut().remove(CONTENT_LENGTH);
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{content_encodings, decode, encode};
+    use crate::http::{header::CONTENT_ENCODING, HeaderMap, HeaderValue};
+    use crate::ContentEncoding;
+    use flate2::write::DeflateEncoder;
+    use std::io::{self, Write};
+
+    const LIMIT: u64 = 1024;
+    const DATA: &[u8] = b"{\"name\": \"Hexilee\", \"name\": \"Hexilee\", \"name\": \"Hexilee\"}";
+
+    #[test]
+    fn round_trip() -> io::Result<()> {
+        for encoding in &[
+            ContentEncoding::Gzip,
+            ContentEncoding::Deflate,
+            ContentEncoding::Brotli,
+        ] {
+            let encoded = encode(*encoding, DATA)?;
+            assert_ne!(DATA, encoded.as_slice());
+            assert_eq!(DATA, decode(*encoding, &encoded, LIMIT)?.as_slice());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn max_decoded_size() -> io::Result<()> {
+        let bomb = vec![0; 1024 * 1024];
+        for encoding in &[
+            ContentEncoding::Gzip,
+            ContentEncoding::Deflate,
+            ContentEncoding::Brotli,
+        ] {
+            let encoded = encode(*encoding, &bomb)?;
+            let err = decode(*encoding, &encoded, 1024).unwrap_err();
+            assert_eq!(io::ErrorKind::InvalidData, err.kind());
+            assert_eq!(bomb.len(), decode(*encoding, &encoded, 1024 * 1024)?.len());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn raw_deflate() -> io::Result<()> {
+        let mut encoder = DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(DATA)?;
+        let encoded = encoder.finish()?;
+        assert_eq!(
+            DATA,
+            decode(ContentEncoding::Deflate, &encoded, LIMIT)?.as_slice()
+        );
+        assert!(decode(ContentEncoding::Gzip, DATA, LIMIT).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn parse_content_encodings() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(Some(vec![]), content_encodings(&headers));
+        headers.append(CONTENT_ENCODING, HeaderValue::from_static("gzip, identity"));
+        headers.append(CONTENT_ENCODING, HeaderValue::from_static("BR"));
+        assert_eq!(
+            Some(vec![ContentEncoding::Gzip, ContentEncoding::Brotli]),
+            content_encodings(&headers)
+        );
+        headers.append(CONTENT_ENCODING, HeaderValue::from_static("zstd"));
+        assert_eq!(None, content_encodings(&headers));
+    }
+}
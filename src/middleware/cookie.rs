@@ -0,0 +1,467 @@
+use super::Middleware;
+use crate::{
+    async_trait,
+    cookie::{Cookie, CookieJar},
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderValue, Request, Response,
+    },
+    url::Url,
+    Body, HttpClient,
+};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use time::Tm;
+
+/// Middleware storing cookies from `Set-Cookie` and sending them back by `Cookie`.
+///
+/// Cookies are matched against the request url by domain, path, expiry and secure attributes,
+/// public suffixes are not checked. A `Cookie` header set by parameters is kept,
+/// cookies in the store are appended to it.
+///
+/// `CookieStore` is a shared handle, clone it to inspect, seed or persist cookies
+/// after it wraps a client.
+///
+/// ```rust
+/// use interfacer_http::middleware::CookieStore;
+/// use interfacer_http::cookie::Cookie;
+///
+/// let store = CookieStore::new();
+/// let url = "https://github.com/login".parse().unwrap();
+/// assert!(store.add(&url, Cookie::parse("session=token; Path=/").unwrap()));
+/// assert_eq!("session=token", store.cookies(&url)[0].to_string());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CookieStore {
+    jars: Arc<Mutex<HashMap<Scope, CookieJar>>>,
+}
+
+/// cookies with the same name are distinguished by domain and path.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+struct Scope {
+    domain: String,
+    host_only: bool,
+    path: String,
+}
+
+impl CookieStore {
+    /// Construct an empty CookieStore.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// store a cookie as if it's set by a response from `url`.
+    ///
+    /// returns false if the cookie is rejected, like its domain doesn't match `url`.
+    /// an expired cookie removes the stored one.
+    pub fn add(&self, url: &Url, cookie: Cookie<'_>) -> bool {
+        let host = match url.host_str() {
+            Some(host) => host.to_lowercase(),
+            None => return false,
+        };
+        let (domain, host_only) = match cookie.domain() {
+            Some(domain) => {
+                let domain = domain.to_lowercase();
+                if !domain_match(&host, &domain) {
+                    return false;
+                }
+                (domain, false)
+            }
+            None => (host, true),
+        };
+        if cookie.secure() == Some(true) && url.scheme() != "https" {
+            return false;
+        }
+        let path = match cookie.path() {
+            Some(path) if path.starts_with('/') => path.to_owned(),
+            _ => default_path(url.path()).to_owned(),
+        };
+        let now = time::now_utc();
+        let expires = match (cookie.max_age(), cookie.expires()) {
+            (Some(max_age), _) => Some(now + max_age),
+            (None, expires) => expires,
+        };
+        let stored = normalize(&cookie, Some(&domain), &path, expires);
+        let scope = Scope {
+            domain,
+            host_only,
+            path,
+        };
+        let mut jars = self.jars.lock().unwrap();
+        match expires {
+            Some(expires) if expires <= now => {
+                if let Some(jar) = jars.get_mut(&scope) {
+                    jar.force_remove(stored);
+                }
+            }
+            _ => jars.entry(scope).or_default().add(stored),
+        }
+        true
+    }
+
+    /// cookies to send to `url`, longer paths first.
+    pub fn cookies(&self, url: &Url) -> Vec<Cookie<'static>> {
+        let host = match url.host_str() {
+            Some(host) => host.to_lowercase(),
+            None => return Vec::new(),
+        };
+        let secure = url.scheme() == "https";
+        let now = time::now_utc();
+        let jars = self.jars.lock().unwrap();
+        let mut scopes = jars
+            .iter()
+            .filter(|(scope, _)| {
+                if scope.host_only {
+                    host == scope.domain
+                } else {
+                    domain_match(&host, &scope.domain)
+                }
+            })
+            .filter(|(scope, _)| path_match(url.path(), &scope.path))
+            .collect::<Vec<_>>();
+        scopes.sort_by_key(|(scope, _)| Reverse(scope.path.len()));
+        scopes
+            .into_iter()
+            .flat_map(|(_, jar)| jar.iter())
+            .filter(|cookie| secure || cookie.secure() != Some(true))
+            .filter(|cookie| !expired(cookie, now))
+            .map(|cookie| Cookie::new(cookie.name().to_owned(), cookie.value().to_owned()))
+            .collect()
+    }
+
+    /// remove all cookies.
+    pub fn clear(&self) {
+        self.jars.lock().unwrap().clear()
+    }
+
+    /// write unexpired cookies, a line for each, formatted as `<url> <Set-Cookie>`.
+    ///
+    /// lines can be read by `load`.
+    pub fn save(&self, mut writer: impl Write) -> io::Result<()> {
+        let now = time::now_utc();
+        let jars = self.jars.lock().unwrap();
+        for (scope, jar) in jars.iter() {
+            for cookie in jar.iter() {
+                if expired(cookie, now) {
+                    continue;
+                }
+                let scheme = if cookie.secure() == Some(true) {
+                    "https"
+                } else {
+                    "http"
+                };
+                // a host-only cookie has no `Domain` attribute.
+                let domain = if scope.host_only {
+                    None
+                } else {
+                    Some(scope.domain.as_str())
+                };
+                let cookie = normalize(cookie, domain, &scope.path, cookie.expires());
+                writeln!(
+                    writer,
+                    "{}://{}{} {}",
+                    scheme, scope.domain, scope.path, cookie
+                )?;
+            }
+        }
+        Ok(())
+    }
+
+    /// read cookies written by `save`.
+    pub fn load(&self, reader: impl BufRead) -> io::Result<()> {
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let mut parts = line.splitn(2, ' ');
+            let url = parts.next().and_then(|url| url.parse::<Url>().ok());
+            let cookie = parts.next().and_then(|cookie| Cookie::parse(cookie).ok());
+            match (url, cookie) {
+                (Some(url), Some(cookie)) => {
+                    self.add(&url, cookie);
+                }
+                _ => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("invalid cookie line: {}", line),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// `Cookie` header for `url`, appended to the `existing` one.
+    fn header(&self, url: &Url, existing: Option<&HeaderValue>) -> Option<HeaderValue> {
+        let mut pairs = Vec::new();
+        if let Some(existing) = existing.and_then(|value| value.to_str().ok()) {
+            pairs.push(existing.to_owned());
+        }
+        pairs.extend(self.cookies(url).iter().map(ToString::to_string));
+        if pairs.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&pairs.join("; ")).ok()
+    }
+
+    /// store cookies from `Set-Cookie` headers, invalid ones are ignored.
+    fn record<T>(&self, url: &Url, resp: &Response<T>) {
+        for value in resp.headers().get_all(SET_COOKIE) {
+            if let Some(cookie) = value
+                .to_str()
+                .ok()
+                .and_then(|value| Cookie::parse(value).ok())
+            {
+                self.add(url, cookie);
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for CookieStore {
+    async fn handle(
+        &self,
+        mut req: Request<Vec<u8>>,
+        next: &C,
+    ) -> Result<Response<Vec<u8>>, C::Err> {
+        let url = match req.uri().to_string().parse::<Url>() {
+            Ok(url) => url,
+            Err(_) => return next.request(req).await,
+        };
+        if let Some(value) = self.header(&url, req.headers().get(COOKIE)) {
+            req.headers_mut().insert(COOKIE, value);
+        }
+        let resp = next.request(req).await?;
+        self.record(&url, &resp);
+        Ok(resp)
+    }
+
+    async fn handle_stream(
+        &self,
+        mut req: Request<Body>,
+        next: &C,
+    ) -> Result<Response<Body>, C::Err> {
+        let url = match req.uri().to_string().parse::<Url>() {
+            Ok(url) => url,
+            Err(_) => return next.request_stream(req).await,
+        };
+        if let Some(value) = self.header(&url, req.headers().get(COOKIE)) {
+            req.headers_mut().insert(COOKIE, value);
+        }
+        let resp = next.request_stream(req).await?;
+        self.record(&url, &resp);
+        Ok(resp)
+    }
+}
+
+/// copy name, value and attributes of `cookie`, with `Max-Age` converted to `Expires`.
+fn normalize(
+    cookie: &Cookie<'_>,
+    domain: Option<&str>,
+    path: &str,
+    expires: Option<Tm>,
+) -> Cookie<'static> {
+    let mut normalized = Cookie::new(cookie.name().to_owned(), cookie.value().to_owned());
+    if let Some(domain) = domain {
+        normalized.set_domain(domain.to_owned());
+    }
+    normalized.set_path(path.to_owned());
+    if let Some(secure) = cookie.secure() {
+        normalized.set_secure(secure);
+    }
+    if let Some(http_only) = cookie.http_only() {
+        normalized.set_http_only(http_only);
+    }
+    if let Some(same_site) = cookie.same_site() {
+        normalized.set_same_site(same_site);
+    }
+    if let Some(expires) = expires {
+        normalized.set_expires(expires);
+    }
+    normalized
+}
+
+fn expired(cookie: &Cookie<'_>, now: Tm) -> bool {
+    match cookie.expires() {
+        Some(expires) => expires <= now,
+        None => false,
+    }
+}
+
+/// whether `host` is `domain` or a subdomain of it, ip addresses only match themselves.
+fn domain_match(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.ends_with(domain)
+            && host[..host.len() - domain.len()].ends_with('.')
+            && host.parse::<IpAddr>().is_err())
+}
+
+/// directory of the request path.
+fn default_path(path: &str) -> &str {
+    match path.rfind('/') {
+        Some(0) | None => "/",
+        Some(index) => &path[..index],
+    }
+}
+
+/// whether `path` is `cookie_path` or under it.
+fn path_match(path: &str, cookie_path: &str) -> bool {
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{default_path, domain_match, path_match, CookieStore};
+    use crate::cookie::Cookie;
+    use crate::http::{
+        header::{COOKIE, SET_COOKIE},
+        Request, Response,
+    };
+    use crate::middleware::HttpClientExt;
+    use crate::mock::{Client, Error};
+    use crate::url::Url;
+    use crate::HttpClient;
+    use futures::executor::block_on;
+
+    fn url(raw: &str) -> Url {
+        raw.parse().unwrap()
+    }
+
+    fn add(store: &CookieStore, raw_url: &str, cookie: &str) -> bool {
+        store.add(&url(raw_url), Cookie::parse(cookie.to_owned()).unwrap())
+    }
+
+    fn header(store: &CookieStore, raw_url: &str) -> String {
+        store
+            .cookies(&url(raw_url))
+            .iter()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join("; ")
+    }
+
+    #[test]
+    fn matches() {
+        assert!(domain_match("github.com", "github.com"));
+        assert!(domain_match("api.github.com", "github.com"));
+        assert!(!domain_match("notgithub.com", "github.com"));
+        assert!(!domain_match("127.0.0.1", "0.0.1"));
+        assert!(path_match("/api/user", "/api"));
+        assert!(path_match("/api/user", "/api/"));
+        assert!(!path_match("/apis", "/api"));
+        assert_eq!("/api", default_path("/api/user"));
+        assert_eq!("/", default_path("/login"));
+    }
+
+    #[test]
+    fn domain() {
+        let store = CookieStore::new();
+        assert!(add(&store, "https://github.com", "host=0"));
+        assert!(add(
+            &store,
+            "https://github.com",
+            "domain=1; Domain=.github.com"
+        ));
+        assert!(!add(
+            &store,
+            "https://github.com",
+            "other=2; Domain=gitlab.com"
+        ));
+        let mut cookies = store.cookies(&url("https://github.com/"));
+        cookies.sort_by(|a, b| a.name().cmp(b.name()));
+        assert_eq!("domain=1", cookies[0].to_string());
+        assert_eq!("host=0", cookies[1].to_string());
+        assert_eq!(2, cookies.len());
+        assert_eq!("domain=1", header(&store, "https://api.github.com/"));
+        assert_eq!("", header(&store, "https://gitlab.com/"));
+    }
+
+    #[test]
+    fn path_and_secure() {
+        let store = CookieStore::new();
+        assert!(add(&store, "https://github.com/api/login", "a=0"));
+        assert!(add(&store, "https://github.com/", "b=1; Path=/; Secure"));
+        assert!(!add(&store, "http://github.com/", "c=2; Secure"));
+        assert_eq!("a=0; b=1", header(&store, "https://github.com/api/user"));
+        assert_eq!("b=1", header(&store, "https://github.com/user"));
+        assert_eq!("", header(&store, "http://github.com/user"));
+    }
+
+    #[test]
+    fn expiry() {
+        let store = CookieStore::new();
+        assert!(add(&store, "https://github.com/", "a=0; Max-Age=60"));
+        assert!(add(
+            &store,
+            "https://github.com/",
+            "b=1; Expires=Wed, 21 Oct 2015 07:28:00 GMT"
+        ));
+        assert_eq!("a=0", header(&store, "https://github.com/"));
+        assert!(add(&store, "https://github.com/", "a=0; Max-Age=0"));
+        assert_eq!("", header(&store, "https://github.com/"));
+    }
+
+    #[test]
+    fn persist() -> std::io::Result<()> {
+        let store = CookieStore::new();
+        add(
+            &store,
+            "https://github.com/api/login",
+            "a=0; Secure; Max-Age=60",
+        );
+        add(&store, "https://github.com/", "b=1; Domain=github.com");
+        let mut data = Vec::new();
+        store.save(&mut data)?;
+        let loaded = CookieStore::new();
+        loaded.load(data.as_slice())?;
+        assert_eq!("a=0; b=1", header(&loaded, "https://github.com/api"));
+        assert_eq!("b=1", header(&loaded, "http://api.github.com/api"));
+        assert!(loaded.load("invalid".as_bytes()).is_err());
+        Ok(())
+    }
+
+    async fn handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        let cookie = req
+            .headers()
+            .get(COOKIE)
+            .map(|value| value.to_str().unwrap().to_owned())
+            .unwrap_or_default();
+        Ok(Response::builder()
+            .header(SET_COOKIE, "session=token; Path=/; HttpOnly")
+            .body(cookie.into_bytes())?)
+    }
+
+    #[test]
+    fn middleware() -> Result<(), Error> {
+        let store = CookieStore::new();
+        let client =
+            Client::new("https://mock.rs".parse()?, handler).with_middleware(store.clone());
+        let request = |cookie: Option<&str>| {
+            let mut builder = Request::builder();
+            builder.uri("https://mock.rs/api");
+            if let Some(cookie) = cookie {
+                builder.header(COOKIE, cookie);
+            }
+            builder.body(Vec::new())
+        };
+        assert!(block_on(client.request(request(None)?))?.body().is_empty());
+        assert_eq!(
+            b"session=token".to_vec(),
+            block_on(client.request(request(None)?))?.into_body()
+        );
+        assert_eq!(
+            b"lang=rust; session=token".to_vec(),
+            block_on(client.request(request(Some("lang=rust"))?))?.into_body()
+        );
+        assert_eq!(1, store.cookies(&url("https://mock.rs/")).len());
+        Ok(())
+    }
+}
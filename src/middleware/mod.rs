@@ -0,0 +1,340 @@
+//! middleware around `HttpClient`.
+//!
+//! A `Middleware` sees every `Request` before it is sent by the wrapped client,
+//! and every `Response` after, it can also short-circuit by returning without calling `next`.
+//!
+//! Streaming requests are buffered before `Middleware::handle` by default,
+//! middlewares not touching bodies override `Middleware::handle_stream` to keep them streaming.
+//!
+//! ### Example
+//!
+//! ```rust,ignore
+//! use interfacer_http::middleware::{HttpClientExt, Middleware};
+//! use interfacer_http::{async_trait, http::{Request, Response}, HttpClient};
+//!
+//! struct Logger;
+//!
+//! #[async_trait]
+//! impl<C: HttpClient> Middleware<C> for Logger {
+//!     async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+//!         let uri = req.uri().clone();
+//!         let resp = next.request(req).await?;
+//!         println!("{} -> {}", uri, resp.status());
+//!         Ok(resp)
+//!     }
+//! }
+//!
+//! // `UserService` is a `http_service` trait.
+//! let service = client.with_middleware(Logger);
+//! service.get_user(0).await?;
+//! ```
+
+pub use self::cookie::CookieStore;
+#[cfg(feature = "compression")]
+pub use compression::Compression;
+#[cfg(any(feature = "serde-full", feature = "serde-json"))]
+pub use oauth2::ClientCredentials;
+pub use rate_limit::RateLimit;
+pub use redirect::{Redirect, RedirectChain, RedirectPolicy};
+pub use retry::{retry_after, Retry};
+
+use crate::{
+    async_trait,
+    http::{Request, Response},
+    Body, Helper, HttpClient, RequestOptions,
+};
+
+#[cfg(feature = "compression")]
+mod compression;
+mod cookie;
+#[cfg(any(feature = "serde-full", feature = "serde-json"))]
+mod oauth2;
+mod rate_limit;
+mod redirect;
+mod retry;
+
+/// Middleware trait.
+#[async_trait]
+pub trait Middleware<C: HttpClient>: Send + Sync {
+    /// handle a request, `next` is the wrapped client.
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err>;
+
+    /// handle a streaming request, `next` is the wrapped client.
+    ///
+    /// The default implementation buffers the request and the response and delegates to `handle`,
+    /// override it to call `next.request_stream` if the middleware doesn't need bodies.
+    async fn handle_stream(&self, req: Request<Body>, next: &C) -> Result<Response<Body>, C::Err> {
+        let (parts, body) = req.into_parts();
+        let req = Request::from_parts(parts, body.into_vec().await?);
+        let (parts, body) = self.handle(req, next).await?.into_parts();
+        Ok(Response::from_parts(parts, body.into()))
+    }
+}
+
+/// `HttpClient` wrapped by a `Middleware`.
+///
+/// It implements `HttpClient`, so it can be wrapped again,
+/// and it implements every `http_service` trait.
+///
+/// `request_stream` goes through `Middleware::handle_stream`,
+/// bodies are **buffered in memory** unless the middleware overrides it.
+/// `CookieStore` and `RateLimit` keep bodies streaming,
+/// `Retry`, `Redirect`, `Compression` and `ClientCredentials` buffer them.
+#[derive(Clone)]
+pub struct Layered<C, M> {
+    inner: C,
+    middleware: M,
+}
+
+impl<C, M> Layered<C, M> {
+    /// wrap `inner` by `middleware`.
+    pub fn new(inner: C, middleware: M) -> Self {
+        Self { inner, middleware }
+    }
+
+    /// reference to the wrapped client.
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+
+    /// reference to the middleware.
+    pub fn middleware(&self) -> &M {
+        &self.middleware
+    }
+
+    /// unwrap into the wrapped client and the middleware.
+    pub fn into_parts(self) -> (C, M) {
+        (self.inner, self.middleware)
+    }
+}
+
+#[async_trait]
+impl<C, M> HttpClient for Layered<C, M>
+where
+    C: HttpClient,
+    M: Middleware<C>,
+{
+    type Err = C::Err;
+
+    async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Self::Err> {
+        self.middleware.handle(req, &self.inner).await
+    }
+
+    async fn request_stream(&self, req: Request<Body>) -> Result<Response<Body>, Self::Err> {
+        self.middleware.handle_stream(req, &self.inner).await
+    }
+
+    fn helper(&self) -> &Helper {
+        self.inner.helper()
+    }
+}
+
+/// Extensional trait for `HttpClient`.
+pub trait HttpClientExt: HttpClient + Sized {
+    /// wrap self by a middleware.
+    ///
+    /// Streaming bodies are buffered by middlewares which don't override `Middleware::handle_stream`.
+    fn with_middleware<M: Middleware<Self>>(self, middleware: M) -> Layered<Self, M> {
+        Layered::new(self, middleware)
+    }
+}
+
+impl<C: HttpClient> HttpClientExt for C {}
+
+/// clone a buffered request, including the `RequestOptions` extension.
+///
+/// **Other extensions are dropped.** `Request` is not `Clone` because its extensions are not,
+/// so extensions attached by callers or outer middlewares are lost in the clone,
+/// middlewares sending cloned requests, like `Retry`, `Redirect` and `ClientCredentials`,
+/// should wrap middlewares reading such extensions rather than be wrapped by them.
+pub fn clone_request(req: &Request<Vec<u8>>) -> Request<Vec<u8>> {
+    let mut cloned = Request::new(req.body().clone());
+    *cloned.method_mut() = req.method().clone();
+    *cloned.uri_mut() = req.uri().clone();
+    *cloned.version_mut() = req.version();
+    *cloned.headers_mut() = req.headers().clone();
+    if let Some(options) = req.extensions().get::<RequestOptions>() {
+        cloned.extensions_mut().insert(options.clone());
+    }
+    cloned
+}
+
+/// Middleware mapping every request, constructed by `map_request`.
+#[derive(Clone)]
+pub struct MapRequest<F>(F);
+
+/// Middleware mapping every response, constructed by `map_response`.
+#[derive(Clone)]
+pub struct MapResponse<F>(F);
+
+/// construct a middleware mapping every request, such as stamping headers.
+///
+/// ```rust
+/// use interfacer_http::middleware::map_request;
+/// use interfacer_http::http::{header::USER_AGENT, HeaderValue, Request};
+///
+/// let stamp = map_request(|mut req: Request<Vec<u8>>| {
+///     req.headers_mut()
+///         .insert(USER_AGENT, HeaderValue::from_static("interfacer-http"));
+///     req
+/// });
+/// ```
+pub fn map_request<F>(f: F) -> MapRequest<F>
+where
+    F: Fn(Request<Vec<u8>>) -> Request<Vec<u8>> + Send + Sync,
+{
+    MapRequest(f)
+}
+
+/// construct a middleware mapping every response.
+pub fn map_response<F>(f: F) -> MapResponse<F>
+where
+    F: Fn(Response<Vec<u8>>) -> Response<Vec<u8>> + Send + Sync,
+{
+    MapResponse(f)
+}
+
+#[async_trait]
+impl<C, F> Middleware<C> for MapRequest<F>
+where
+    C: HttpClient,
+    F: Fn(Request<Vec<u8>>) -> Request<Vec<u8>> + Send + Sync,
+{
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        next.request((self.0)(req)).await
+    }
+}
+
+#[async_trait]
+impl<C, F> Middleware<C> for MapResponse<F>
+where
+    C: HttpClient,
+    F: Fn(Response<Vec<u8>>) -> Response<Vec<u8>> + Send + Sync,
+{
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        Ok((self.0)(next.request(req).await?))
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{map_request, map_response, HttpClientExt, Middleware};
+    use crate::http::{header::USER_AGENT, HeaderValue, Request, Response, StatusCode};
+    use crate::mock::{Client, Error};
+    use crate::{async_trait, Body, HttpClient};
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    async fn echo_user_agent(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        let body = match req.headers().get(USER_AGENT) {
+            Some(value) => value.as_bytes().to_vec(),
+            None => Vec::new(),
+        };
+        Ok(Response::builder().status(200).body(body)?)
+    }
+
+    fn request() -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder().uri("https://mock.rs").body(Vec::new())?)
+    }
+
+    #[derive(Default)]
+    struct Counter(AtomicUsize);
+
+    #[async_trait]
+    impl<C: HttpClient> Middleware<C> for Counter {
+        async fn handle(
+            &self,
+            req: Request<Vec<u8>>,
+            next: &C,
+        ) -> Result<Response<Vec<u8>>, C::Err> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            next.request(req).await
+        }
+    }
+
+    struct Forbidden;
+
+    #[async_trait]
+    impl<C: HttpClient> Middleware<C> for Forbidden {
+        async fn handle(
+            &self,
+            _req: Request<Vec<u8>>,
+            _next: &C,
+        ) -> Result<Response<Vec<u8>>, C::Err> {
+            Ok(Response::builder().status(403).body(Vec::new())?)
+        }
+    }
+
+    #[test]
+    fn map() -> Result<(), Error> {
+        let client = Client::new("https://mock.rs".parse()?, echo_user_agent)
+            .with_middleware(map_request(|mut req| {
+                req.headers_mut()
+                    .insert(USER_AGENT, HeaderValue::from_static("interfacer-http"));
+                req
+            }))
+            .with_middleware(map_response(|mut resp| {
+                *resp.status_mut() = StatusCode::CREATED;
+                resp
+            }));
+        let resp = block_on(client.request(request()?))?;
+        assert_eq!(201, resp.status());
+        assert_eq!(b"interfacer-http".to_vec(), resp.into_body());
+        Ok(())
+    }
+
+    #[test]
+    fn short_circuit() -> Result<(), Error> {
+        let client = Client::new("https://mock.rs".parse()?, echo_user_agent)
+            .with_middleware(Counter::default())
+            .with_middleware(Forbidden);
+        let resp = block_on(client.request(request()?))?;
+        assert_eq!(403, resp.status());
+        assert_eq!(0, client.inner().middleware().0.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    struct Streaming;
+
+    #[async_trait]
+    impl<C: HttpClient> Middleware<C> for Streaming {
+        async fn handle(
+            &self,
+            _req: Request<Vec<u8>>,
+            _next: &C,
+        ) -> Result<Response<Vec<u8>>, C::Err> {
+            unreachable!("streaming requests should not be buffered")
+        }
+
+        async fn handle_stream(
+            &self,
+            req: Request<Body>,
+            next: &C,
+        ) -> Result<Response<Body>, C::Err> {
+            next.request_stream(req).await
+        }
+    }
+
+    #[test]
+    fn stream() -> Result<(), Error> {
+        let client =
+            Client::new("https://mock.rs".parse()?, echo_user_agent).with_middleware(Streaming);
+        let resp = block_on(client.request_stream(Request::new(Default::default())))?;
+        assert_eq!(200, resp.status());
+        Ok(())
+    }
+
+    #[test]
+    fn delegate() -> Result<(), Error> {
+        let client = Client::new("https://mock.rs".parse()?, echo_user_agent)
+            .with_middleware(Counter::default());
+        block_on(client.request(request()?))?;
+        block_on(client.request_stream(Request::new(Default::default())))?;
+        assert_eq!(2, client.middleware().0.load(Ordering::SeqCst));
+        assert_eq!(
+            "https://mock.rs/api",
+            client.helper().parse_uri("/api")?.as_str()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,348 @@
+use super::{clone_request, Middleware};
+use crate::{
+    async_trait,
+    http::{
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
+        Method, Request, Response, StatusCode,
+    },
+    mime,
+    url::{form_urlencoded, Url},
+    AuthError, Credential, HttpClient, Token,
+};
+use futures::lock::Mutex;
+use serde_json::Value;
+use std::sync::Arc;
+use std::time::Duration;
+
+const DEFAULT_LEEWAY: Duration = Duration::from_secs(30);
+
+/// Middleware authenticating requests by OAuth2 client credentials grant.
+///
+/// The access token is fetched from the token endpoint through the wrapped client,
+/// and cached until `leeway` before it expires, concurrent requests wait for a single fetch.
+/// It's sent by `Authorization: Bearer <token>` unless the request has an `Authorization`.
+///
+/// A request rejected by 401 is retried once with a new token,
+/// the retried one is sent by `clone_request` and carries no extension but `RequestOptions`.
+///
+/// ```rust
+/// use interfacer_http::middleware::ClientCredentials;
+///
+/// let oauth2 = ClientCredentials::new(
+///     "https://auth.mock.rs/oauth/token".parse().unwrap(),
+///     "client-id",
+///     "client-secret",
+/// )
+/// .with_scope("read write");
+/// ```
+#[derive(Clone)]
+pub struct ClientCredentials {
+    token_url: Url,
+    client_id: String,
+    client_secret: String,
+    scope: Option<String>,
+    leeway: Duration,
+    token: Arc<Mutex<Option<Token>>>,
+}
+
+impl ClientCredentials {
+    /// Construct with the token endpoint and client credentials.
+    pub fn new(
+        token_url: Url,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        Self {
+            token_url,
+            client_id: client_id.into(),
+            client_secret: client_secret.into(),
+            scope: None,
+            leeway: DEFAULT_LEEWAY,
+            token: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// request a scope.
+    pub fn with_scope(self, scope: impl Into<String>) -> Self {
+        Self {
+            scope: Some(scope.into()),
+            ..self
+        }
+    }
+
+    /// refresh tokens expiring within `leeway`, 30s by default.
+    pub fn with_leeway(self, leeway: Duration) -> Self {
+        Self { leeway, ..self }
+    }
+
+    /// the cached token, fetch a new one by `client` if it's absent, expired or `rejected`.
+    async fn token<C: HttpClient>(
+        &self,
+        client: &C,
+        rejected: Option<&str>,
+    ) -> Result<String, C::Err> {
+        let mut token = self.token.lock().await;
+        match token.as_ref() {
+            Some(cached) if !cached.is_expired(self.leeway) && Some(cached.value()) != rejected => {
+                Ok(cached.value().to_owned())
+            }
+            _ => {
+                let fetched = self.fetch(client).await?;
+                let value = fetched.value().to_owned();
+                *token = Some(fetched);
+                Ok(value)
+            }
+        }
+    }
+
+    /// form body of token request.
+    fn form(&self) -> Vec<u8> {
+        let mut form = form_urlencoded::Serializer::new(String::new());
+        form.append_pair("grant_type", "client_credentials");
+        if let Some(scope) = &self.scope {
+            form.append_pair("scope", scope);
+        }
+        form.finish().into_bytes()
+    }
+
+    async fn fetch<C: HttpClient>(&self, client: &C) -> Result<Token, C::Err> {
+        let credential = Credential::Basic {
+            username: self.client_id.clone(),
+            password: Some(self.client_secret.clone()),
+        };
+        let req = Request::builder()
+            .method(Method::POST)
+            .uri(self.token_url.as_str())
+            .header(CONTENT_TYPE, mime::APPLICATION_WWW_FORM_URLENCODED.as_ref())
+            .header(ACCEPT, mime::APPLICATION_JSON.as_ref())
+            .header(AUTHORIZATION, credential.to_header_value()?)
+            .body(self.form())?;
+        let resp = client.request(req).await?;
+        if !resp.status().is_success() {
+            return Err(AuthError::provider(format!(
+                "token endpoint responds {}: {}",
+                resp.status(),
+                String::from_utf8_lossy(resp.body())
+            ))
+            .into());
+        }
+        Ok(parse_token(resp.body())?)
+    }
+}
+
+/// parse token response like `{"access_token": "token", "token_type": "bearer", "expires_in": 3600}`.
+fn parse_token(body: &[u8]) -> Result<Token, AuthError> {
+    let value: Value = serde_json::from_slice(body).map_err(AuthError::provider)?;
+    let access_token = value
+        .get("access_token")
+        .and_then(Value::as_str)
+        .ok_or_else(|| AuthError::provider("access_token not found in token response"))?;
+    let token = Token::new(access_token);
+    Ok(match value.get("expires_in").and_then(Value::as_u64) {
+        Some(expires_in) => token.with_expires_in(Duration::from_secs(expires_in)),
+        None => token,
+    })
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for ClientCredentials {
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        if req.headers().contains_key(AUTHORIZATION) {
+            return next.request(req).await;
+        }
+        let mut retry = clone_request(&req);
+        let mut req = req;
+        let token = self.token(next, None).await?;
+        req.headers_mut().insert(
+            AUTHORIZATION,
+            Credential::Bearer(token.clone()).to_header_value()?,
+        );
+        let resp = next.request(req).await?;
+        if resp.status() != StatusCode::UNAUTHORIZED {
+            return Ok(resp);
+        }
+        let token = self.token(next, Some(&token)).await?;
+        retry
+            .headers_mut()
+            .insert(AUTHORIZATION, Credential::Bearer(token).to_header_value()?);
+        next.request(retry).await
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{parse_token, ClientCredentials};
+    use crate::http::{
+        header::{AUTHORIZATION, CONTENT_TYPE},
+        Request, Response, StatusCode,
+    };
+    use crate::middleware::HttpClientExt;
+    use crate::mock::{Client, Error};
+    use crate::HttpClient;
+    use futures::executor::block_on;
+    use futures::future::join_all;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    const TOKEN_URL: &str = "https://auth.mock.rs/oauth/token";
+
+    fn request(path: &str) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .uri(format!("https://mock.rs{}", path).as_str())
+            .body(Vec::new())?)
+    }
+
+    fn token_response(token: &str, expires_in: u64) -> Result<Response<Vec<u8>>, Error> {
+        Ok(Response::builder()
+            .header(CONTENT_TYPE, "application/json")
+            .body(
+                format!(
+                    r#"{{"access_token": "{}", "token_type": "bearer", "expires_in": {}}}"#,
+                    token, expires_in
+                )
+                .into_bytes(),
+            )?)
+    }
+
+    fn status(status: StatusCode) -> Result<Response<Vec<u8>>, Error> {
+        Ok(Response::builder().status(status).body(Vec::new())?)
+    }
+
+    #[test]
+    fn test_parse_token() {
+        let token = parse_token(br#"{"access_token": "token", "expires_in": 60}"#).unwrap();
+        assert_eq!("token", token.value());
+        assert!(!token.is_expired(Duration::from_secs(30)));
+        assert!(token.is_expired(Duration::from_secs(60)));
+        assert!(parse_token(br#"{"token": "token"}"#).is_err());
+        assert!(parse_token(b"token").is_err());
+    }
+
+    fn cached(req: Request<Vec<u8>>, fetches: &AtomicUsize) -> Result<Response<Vec<u8>>, Error> {
+        if req.uri() == TOKEN_URL {
+            assert_eq!("POST", req.method());
+            assert_eq!(
+                "Basic aWQ6c2VjcmV0",
+                req.headers()[AUTHORIZATION].to_str().unwrap()
+            );
+            assert_eq!(
+                b"grant_type=client_credentials&scope=read+write".to_vec(),
+                req.into_body()
+            );
+            let count = fetches.fetch_add(1, Ordering::SeqCst);
+            return token_response(&format!("token-{}", count), 3600);
+        }
+        match req.headers()[AUTHORIZATION].to_str().unwrap() {
+            "Bearer token-0" => status(StatusCode::OK),
+            _ => status(StatusCode::FORBIDDEN),
+        }
+    }
+
+    #[test]
+    fn cache_token() -> Result<(), Error> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let handler = move |req| {
+            let resp = cached(req, &counter);
+            async move { resp }
+        };
+        let client = Client::new("https://mock.rs".parse()?, handler).with_middleware(
+            ClientCredentials::new(TOKEN_URL.parse()?, "id", "secret").with_scope("read write"),
+        );
+        let resps = block_on(join_all(
+            (0..3).map(|_| client.request(request("/api").unwrap())),
+        ));
+        for resp in resps {
+            assert_eq!(StatusCode::OK, resp?.status());
+        }
+        assert_eq!(1, fetches.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    fn expired(req: Request<Vec<u8>>, fetches: &AtomicUsize) -> Result<Response<Vec<u8>>, Error> {
+        if req.uri() == TOKEN_URL {
+            let count = fetches.fetch_add(1, Ordering::SeqCst);
+            return token_response(&format!("token-{}", count), 10);
+        }
+        status(StatusCode::OK)
+    }
+
+    #[test]
+    fn refresh_expired() -> Result<(), Error> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let counter = fetches.clone();
+        let handler = move |req| {
+            let resp = expired(req, &counter);
+            async move { resp }
+        };
+        let client = Client::new("https://mock.rs".parse()?, handler).with_middleware(
+            ClientCredentials::new(TOKEN_URL.parse()?, "id", "secret")
+                .with_leeway(Duration::from_secs(10)),
+        );
+        block_on(client.request(request("/api")?))?;
+        block_on(client.request(request("/api")?))?;
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    fn rejected(
+        req: Request<Vec<u8>>,
+        fetches: &AtomicUsize,
+        calls: &AtomicUsize,
+    ) -> Result<Response<Vec<u8>>, Error> {
+        if req.uri() == TOKEN_URL {
+            let count = fetches.fetch_add(1, Ordering::SeqCst);
+            return token_response(&format!("token-{}", count), 3600);
+        }
+        calls.fetch_add(1, Ordering::SeqCst);
+        match (
+            req.uri().path(),
+            req.headers()[AUTHORIZATION].to_str().unwrap(),
+        ) {
+            ("/api", "Bearer token-1") => status(StatusCode::OK),
+            _ => status(StatusCode::UNAUTHORIZED),
+        }
+    }
+
+    #[test]
+    fn retry_once_on_unauthorized() -> Result<(), Error> {
+        let fetches = Arc::new(AtomicUsize::new(0));
+        let calls = Arc::new(AtomicUsize::new(0));
+        let (fetch_counter, call_counter) = (fetches.clone(), calls.clone());
+        let handler = move |req| {
+            let resp = rejected(req, &fetch_counter, &call_counter);
+            async move { resp }
+        };
+        let client = Client::new("https://mock.rs".parse()?, handler)
+            .with_middleware(ClientCredentials::new(TOKEN_URL.parse()?, "id", "secret"));
+        assert_eq!(
+            StatusCode::OK,
+            block_on(client.request(request("/api")?))?.status()
+        );
+        assert_eq!(2, fetches.load(Ordering::SeqCst));
+        assert_eq!(2, calls.load(Ordering::SeqCst));
+        assert_eq!(
+            StatusCode::UNAUTHORIZED,
+            block_on(client.request(request("/admin")?))?.status()
+        );
+        assert_eq!(3, fetches.load(Ordering::SeqCst));
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    async fn unavailable(_req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        status(StatusCode::SERVICE_UNAVAILABLE)
+    }
+
+    #[test]
+    fn token_endpoint_error() -> Result<(), Error> {
+        let client = Client::new("https://mock.rs".parse()?, unavailable)
+            .with_middleware(ClientCredentials::new(TOKEN_URL.parse()?, "id", "secret"));
+        match block_on(client.request(request("/api")?)) {
+            Err(Error::AuthError(err)) => assert!(err.to_string().contains("503")),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,501 @@
+use super::{retry_after, Middleware};
+use crate::{
+    async_trait,
+    http::{Request, Response, StatusCode},
+    Body, HttpClient,
+};
+use futures::future::poll_fn;
+use futures::task::{Context, Poll, Waker};
+use futures_timer::Delay;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const DEFAULT_MAX_PAUSE: Duration = Duration::from_secs(60);
+
+/// Middleware limiting rate and concurrency of requests.
+///
+/// Requests are throttled by a token bucket refilled by `requests` per `interval`,
+/// holding `burst` tokens at most, and by a semaphore of max in-flight requests.
+/// Limits are shared by all requests, or kept for each host if `per_host` is set.
+///
+/// A `429 Too Many Requests` with `Retry-After` pauses all requests sharing its limits,
+/// the response itself is returned, wrap `RateLimit` by `Retry` to retry it.
+/// The pause is capped by the max pause, 60s by default.
+///
+/// Clones of a `RateLimit` share their limits.
+///
+/// ```rust
+/// use interfacer_http::middleware::RateLimit;
+/// use std::time::Duration;
+///
+/// let rate_limit = RateLimit::new()
+///     .with_rate(100, Duration::from_secs(60))
+///     .with_burst(10)
+///     .with_max_in_flight(4)
+///     .with_per_host(true)
+///     .with_max_pause(Duration::from_secs(30));
+/// ```
+#[derive(Debug, Clone)]
+pub struct RateLimit {
+    rate: Option<(u32, Duration)>,
+    burst: Option<u32>,
+    max_in_flight: Option<usize>,
+    per_host: bool,
+    retry_after: bool,
+    max_pause: Duration,
+    limiters: Arc<Mutex<HashMap<String, Arc<Limiter>>>>,
+}
+
+impl RateLimit {
+    /// Construct a RateLimit without any limit.
+    pub fn new() -> Self {
+        Self {
+            rate: None,
+            burst: None,
+            max_in_flight: None,
+            per_host: false,
+            retry_after: true,
+            max_pause: DEFAULT_MAX_PAUSE,
+            limiters: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RateLimit {
+    /// allow `requests` per `interval`.
+    ///
+    /// Zero `requests` or zero `interval` means no rate limit.
+    pub fn with_rate(self, requests: u32, interval: Duration) -> Self {
+        let rate = if requests == 0 || interval == Duration::from_secs(0) {
+            None
+        } else {
+            Some((requests, interval))
+        };
+        Self { rate, ..self }
+    }
+
+    /// set capacity of the token bucket, `requests` of `with_rate` by default.
+    pub fn with_burst(self, burst: u32) -> Self {
+        Self {
+            burst: Some(burst),
+            ..self
+        }
+    }
+
+    /// set max in-flight requests.
+    ///
+    /// Zero is treated as one, otherwise no request could ever be sent.
+    pub fn with_max_in_flight(self, max_in_flight: usize) -> Self {
+        Self {
+            max_in_flight: Some(max_in_flight),
+            ..self
+        }
+    }
+
+    /// keep limits for each host or not.
+    pub fn with_per_host(self, per_host: bool) -> Self {
+        Self { per_host, ..self }
+    }
+
+    /// pause on `429` with `Retry-After` or not.
+    pub fn with_retry_after(self, retry_after: bool) -> Self {
+        Self {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// set max pause on `429` with `Retry-After`.
+    pub fn with_max_pause(self, max_pause: Duration) -> Self {
+        Self { max_pause, ..self }
+    }
+
+    /// pause the limiter on `429` with `Retry-After`.
+    fn check<T>(&self, limiter: &Limiter, resp: &Response<T>) {
+        if self.retry_after && resp.status() == StatusCode::TOO_MANY_REQUESTS {
+            if let Some(delay) = retry_after(resp) {
+                limiter.pause(delay);
+            }
+        }
+    }
+
+    /// limiter of the request.
+    fn limiter<T>(&self, req: &Request<T>) -> Arc<Limiter> {
+        let key = match req.uri().authority_part() {
+            Some(authority) if self.per_host => authority.as_str().to_ascii_lowercase(),
+            _ => String::new(),
+        };
+        let mut limiters = self.limiters.lock().unwrap();
+        limiters
+            .entry(key)
+            .or_insert_with(|| Arc::new(Limiter::new(self)))
+            .clone()
+    }
+}
+
+/// token bucket and semaphore of a host, or of all hosts.
+#[derive(Debug)]
+struct Limiter {
+    bucket: Mutex<Bucket>,
+    semaphore: Option<Semaphore>,
+    max_pause: Duration,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// tokens per second, None means unlimited.
+    rate: Option<f64>,
+    capacity: f64,
+    tokens: f64,
+    updated: Instant,
+    paused_until: Option<Instant>,
+}
+
+impl Limiter {
+    fn new(config: &RateLimit) -> Self {
+        let (rate, capacity) = match config.rate {
+            Some((requests, interval)) => (
+                Some(f64::from(requests) / interval.as_secs_f64()),
+                f64::from(config.burst.unwrap_or(requests).max(1)),
+            ),
+            None => (None, 0.0),
+        };
+        Self {
+            bucket: Mutex::new(Bucket {
+                rate,
+                capacity,
+                tokens: capacity,
+                updated: Instant::now(),
+                paused_until: None,
+            }),
+            semaphore: config.max_in_flight.map(Semaphore::new),
+            max_pause: config.max_pause,
+        }
+    }
+
+    /// wait until a token is taken.
+    async fn take(&self) {
+        loop {
+            let delay = match self.bucket.lock().unwrap().take(Instant::now()) {
+                Some(delay) => delay,
+                None => return,
+            };
+            Delay::new(delay).await;
+        }
+    }
+
+    /// pause until `delay` elapses, capped by the max pause.
+    fn pause(&self, delay: Duration) {
+        let until = match Instant::now().checked_add(delay.min(self.max_pause)) {
+            Some(until) => until,
+            None => return,
+        };
+        let mut bucket = self.bucket.lock().unwrap();
+        match bucket.paused_until {
+            Some(paused_until) if paused_until >= until => (),
+            _ => bucket.paused_until = Some(until),
+        }
+    }
+}
+
+impl Bucket {
+    /// take a token, or return the delay before the next try.
+    fn take(&mut self, now: Instant) -> Option<Duration> {
+        if let Some(paused_until) = self.paused_until {
+            if paused_until > now {
+                return Some(paused_until - now);
+            }
+            self.paused_until = None;
+        }
+        let rate = self.rate?;
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(self.capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - self.tokens) / rate))
+        }
+    }
+}
+
+/// async counting semaphore.
+#[derive(Debug)]
+struct Semaphore {
+    state: Mutex<SemaphoreState>,
+}
+
+#[derive(Debug)]
+struct SemaphoreState {
+    permits: usize,
+    waiters: Vec<Waker>,
+}
+
+/// permit of `Semaphore`, released when dropped.
+struct Permit<'a>(&'a Semaphore);
+
+impl Semaphore {
+    fn new(permits: usize) -> Self {
+        Self {
+            state: Mutex::new(SemaphoreState {
+                permits: permits.max(1),
+                waiters: Vec::new(),
+            }),
+        }
+    }
+
+    async fn acquire(&self) -> Permit<'_> {
+        poll_fn(|cx: &mut Context<'_>| {
+            let mut state = self.state.lock().unwrap();
+            if state.permits > 0 {
+                state.permits -= 1;
+                Poll::Ready(Permit(self))
+            } else {
+                if !state
+                    .waiters
+                    .iter()
+                    .any(|waker| waker.will_wake(cx.waker()))
+                {
+                    state.waiters.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.0.state.lock().unwrap();
+            state.permits += 1;
+            state.waiters.drain(..).collect::<Vec<_>>()
+        };
+        for waker in waiters {
+            waker.wake();
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for RateLimit {
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        let limiter = self.limiter(&req);
+        let _permit = match &limiter.semaphore {
+            Some(semaphore) => Some(semaphore.acquire().await),
+            None => None,
+        };
+        limiter.take().await;
+        let resp = next.request(req).await?;
+        self.check(&limiter, &resp);
+        Ok(resp)
+    }
+
+    async fn handle_stream(&self, req: Request<Body>, next: &C) -> Result<Response<Body>, C::Err> {
+        let limiter = self.limiter(&req);
+        let _permit = match &limiter.semaphore {
+            Some(semaphore) => Some(semaphore.acquire().await),
+            None => None,
+        };
+        limiter.take().await;
+        let resp = next.request_stream(req).await?;
+        self.check(&limiter, &resp);
+        Ok(resp)
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{Bucket, Limiter, RateLimit};
+    use crate::http::{header::RETRY_AFTER, Request, Response, StatusCode};
+    use crate::middleware::HttpClientExt;
+    use crate::mock::{Client, Error};
+    use crate::HttpClient;
+    use futures::executor::block_on;
+    use futures::future::join_all;
+    use futures_timer::Delay;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn bucket(requests: u32, interval: Duration, burst: u32) -> Bucket {
+        let rate_limit = RateLimit::new()
+            .with_rate(requests, interval)
+            .with_burst(burst);
+        Limiter::new(&rate_limit).bucket.into_inner().unwrap()
+    }
+
+    #[test]
+    fn token_bucket() {
+        let mut bucket = bucket(10, Duration::from_secs(1), 2);
+        let now = bucket.updated;
+        assert_eq!(None, bucket.take(now));
+        assert_eq!(None, bucket.take(now));
+        assert_eq!(Some(Duration::from_millis(100)), bucket.take(now));
+        assert_eq!(None, bucket.take(now + Duration::from_millis(100)));
+        let later = now + Duration::from_secs(10);
+        assert_eq!(None, bucket.take(later));
+        assert_eq!(None, bucket.take(later));
+        assert!(bucket.take(later).is_some());
+    }
+
+    #[test]
+    fn pause() {
+        let mut bucket = bucket(10, Duration::from_secs(1), 10);
+        let now = Instant::now();
+        bucket.paused_until = Some(now + Duration::from_secs(1));
+        assert_eq!(Some(Duration::from_secs(1)), bucket.take(now));
+        assert_eq!(None, bucket.take(now + Duration::from_secs(1)));
+        assert_eq!(None, bucket.paused_until);
+    }
+
+    #[test]
+    fn zero_requests() {
+        let rate_limit = RateLimit::new().with_rate(0, Duration::from_secs(1));
+        let mut bucket = Limiter::new(&rate_limit).bucket.into_inner().unwrap();
+        let now = bucket.updated;
+        for _ in 0..3 {
+            assert_eq!(None, bucket.take(now));
+        }
+    }
+
+    #[test]
+    fn zero_interval() {
+        let rate_limit = RateLimit::new().with_rate(10, Duration::from_secs(0));
+        let mut bucket = Limiter::new(&rate_limit).bucket.into_inner().unwrap();
+        let now = bucket.updated;
+        for _ in 0..20 {
+            assert_eq!(None, bucket.take(now));
+        }
+    }
+
+    #[test]
+    fn max_pause() {
+        let limiter = Limiter::new(&RateLimit::new().with_max_pause(Duration::from_secs(1)));
+        limiter.pause(Duration::from_secs(u64::MAX));
+        let now = Instant::now();
+        let delay = limiter.bucket.lock().unwrap().take(now).unwrap();
+        assert!(delay <= Duration::from_secs(1));
+    }
+
+    fn request(uri: &str) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder().uri(uri).body(Vec::new())?)
+    }
+
+    fn ok() -> Result<Response<Vec<u8>>, Error> {
+        Ok(Response::builder().body(Vec::new())?)
+    }
+
+    async fn instant(_req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        ok()
+    }
+
+    #[test]
+    fn rate() -> Result<(), Error> {
+        let client = Client::new("https://mock.rs".parse()?, instant).with_middleware(
+            RateLimit::new()
+                .with_rate(1, Duration::from_millis(50))
+                .with_burst(2),
+        );
+        let start = Instant::now();
+        for _ in 0..4 {
+            block_on(client.request(request("https://mock.rs")?))?;
+        }
+        assert!(start.elapsed() >= Duration::from_millis(100));
+        Ok(())
+    }
+
+    async fn slow(
+        req: Request<Vec<u8>>,
+        in_flight: Arc<AtomicUsize>,
+        max: Arc<AtomicUsize>,
+    ) -> Result<Response<Vec<u8>>, Error> {
+        let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+        if current > max.load(Ordering::SeqCst) {
+            max.store(current, Ordering::SeqCst);
+        }
+        Delay::new(Duration::from_millis(20)).await;
+        in_flight.fetch_sub(1, Ordering::SeqCst);
+        assert!(req.uri().host().is_some());
+        ok()
+    }
+
+    #[test]
+    fn max_in_flight() -> Result<(), Error> {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let counter = max.clone();
+        let handler = move |req| slow(req, in_flight.clone(), counter.clone());
+        let client = Client::new("https://mock.rs".parse()?, handler)
+            .with_middleware(RateLimit::new().with_max_in_flight(2));
+        let resps = block_on(join_all(
+            (0..5).map(|_| client.request(request("https://mock.rs").unwrap())),
+        ));
+        for resp in resps {
+            resp?;
+        }
+        assert_eq!(2, max.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    #[test]
+    fn per_host() -> Result<(), Error> {
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max = Arc::new(AtomicUsize::new(0));
+        let counter = max.clone();
+        let handler = move |req| slow(req, in_flight.clone(), counter.clone());
+        let client = Client::new("https://mock.rs".parse()?, handler)
+            .with_middleware(RateLimit::new().with_max_in_flight(1).with_per_host(true));
+        let uris = [
+            "https://a.mock.rs",
+            "https://b.mock.rs",
+            "https://a.mock.rs",
+        ];
+        let resps = block_on(join_all(
+            uris.iter().map(|uri| client.request(request(uri).unwrap())),
+        ));
+        for resp in resps {
+            resp?;
+        }
+        assert_eq!(2, max.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    fn limited(calls: &AtomicUsize) -> Result<Response<Vec<u8>>, Error> {
+        match calls.fetch_add(1, Ordering::SeqCst) {
+            0 => Ok(Response::builder()
+                .status(StatusCode::TOO_MANY_REQUESTS)
+                .header(RETRY_AFTER, "1")
+                .body(Vec::new())?),
+            _ => ok(),
+        }
+    }
+
+    #[test]
+    fn honor_retry_after() -> Result<(), Error> {
+        let calls = AtomicUsize::new(0);
+        let handler = move |_req: Request<Vec<u8>>| {
+            let resp = limited(&calls);
+            async move { resp }
+        };
+        let client =
+            Client::new("https://mock.rs".parse()?, handler).with_middleware(RateLimit::new());
+        let start = Instant::now();
+        let resp = block_on(client.request(request("https://mock.rs")?))?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert!(start.elapsed() < Duration::from_secs(1));
+        let resp = block_on(client.request(request("https://mock.rs")?))?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert!(start.elapsed() >= Duration::from_secs(1));
+        Ok(())
+    }
+}
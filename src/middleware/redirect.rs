@@ -0,0 +1,314 @@
+use super::{clone_request, Middleware};
+use crate::{
+    async_trait,
+    http::{
+        self,
+        header::{
+            AUTHORIZATION, CONTENT_LENGTH, CONTENT_TYPE, COOKIE, HOST, LOCATION,
+            PROXY_AUTHORIZATION,
+        },
+        Method, Request, Response, StatusCode, Uri,
+    },
+    url::Url,
+    HttpClient,
+};
+
+const DEFAULT_MAX_HOPS: usize = 10;
+
+/// Which redirects to follow.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum RedirectPolicy {
+    /// follow redirects to the same origin only.
+    SameOrigin,
+    /// follow all redirects, credentials are stripped across origins.
+    All,
+}
+
+/// Urls visited by a request following redirects, in the extensions of response.
+///
+/// ```rust,ignore
+/// let resp = client.with_middleware(Redirect::new()).get_user(0).await?;
+/// let chain = resp.extensions().get::<RedirectChain>().unwrap();
+/// println!("{} -> {}", chain.original_url(), chain.final_url());
+/// ```
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct RedirectChain {
+    urls: Vec<Url>,
+}
+
+impl RedirectChain {
+    /// the requested url.
+    pub fn original_url(&self) -> &Url {
+        &self.urls[0]
+    }
+
+    /// url of the final response.
+    pub fn final_url(&self) -> &Url {
+        &self.urls[self.urls.len() - 1]
+    }
+
+    /// all visited urls, from the original one to the final one.
+    pub fn urls(&self) -> &[Url] {
+        &self.urls
+    }
+
+    /// count of followed redirects.
+    pub fn hops(&self) -> usize {
+        self.urls.len() - 1
+    }
+}
+
+/// Middleware following 301, 302, 303, 307 and 308 redirects.
+///
+/// 303 turns methods except HEAD into GET without body, so does 301 and 302 to POST;
+/// 307 and 308 keep method and body.
+/// `Authorization`, `Proxy-Authorization` and `Cookie` are stripped across origins.
+///
+/// The last redirect response is returned if hops exceed the max, or its redirect is not allowed.
+/// Wrap a `CookieStore` by `Redirect` to record cookies of each hop.
+/// Redirected requests are rebuilt by `clone_request`, they carry no extension but `RequestOptions`.
+///
+/// ```rust
+/// use interfacer_http::middleware::{Redirect, RedirectPolicy};
+///
+/// let redirect = Redirect::new()
+///     .with_max_hops(5)
+///     .with_policy(RedirectPolicy::SameOrigin);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Redirect {
+    max_hops: usize,
+    policy: RedirectPolicy,
+}
+
+impl Redirect {
+    /// Construct a default Redirect.
+    ///
+    /// At most 10 hops, follow all redirects.
+    pub fn new() -> Self {
+        Self {
+            max_hops: DEFAULT_MAX_HOPS,
+            policy: RedirectPolicy::All,
+        }
+    }
+}
+
+impl Default for Redirect {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Redirect {
+    /// set max hops.
+    pub fn with_max_hops(self, max_hops: usize) -> Self {
+        Self { max_hops, ..self }
+    }
+
+    /// set redirect policy.
+    pub fn with_policy(self, policy: RedirectPolicy) -> Self {
+        Self { policy, ..self }
+    }
+
+    /// target of a followable redirect response.
+    fn location<T>(&self, url: &Url, resp: &Response<T>) -> Option<Url> {
+        match resp.status() {
+            StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT => (),
+            _ => return None,
+        }
+        let location = url
+            .join(resp.headers().get(LOCATION)?.to_str().ok()?)
+            .ok()?;
+        match self.policy {
+            RedirectPolicy::SameOrigin if location.origin() != url.origin() => None,
+            _ => Some(location),
+        }
+    }
+}
+
+/// rewrite `req` sent to `from` as redirected by `status` to `to`.
+fn redirect_request(
+    mut req: Request<Vec<u8>>,
+    status: StatusCode,
+    from: &Url,
+    to: &Url,
+) -> Result<Request<Vec<u8>>, http::Error> {
+    *req.uri_mut() = to.as_str().parse::<Uri>()?;
+    let into_get = match status {
+        StatusCode::SEE_OTHER => req.method() != Method::HEAD,
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => req.method() == Method::POST,
+        _ => false,
+    };
+    if into_get {
+        *req.method_mut() = Method::GET;
+        req.body_mut().clear();
+        req.headers_mut().remove(CONTENT_TYPE);
+        req.headers_mut().remove(CONTENT_LENGTH);
+    }
+    req.headers_mut().remove(HOST);
+    if from.origin() != to.origin() {
+        for name in &[AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE] {
+            req.headers_mut().remove(name);
+        }
+    }
+    Ok(req)
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for Redirect {
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        let mut url = match req.uri().to_string().parse::<Url>() {
+            Ok(url) => url,
+            Err(_) => return next.request(req).await,
+        };
+        let mut urls = vec![url.clone()];
+        let mut req = req;
+        loop {
+            let sent = clone_request(&req);
+            let mut resp = next.request(req).await?;
+            let location = match self.location(&url, &resp) {
+                Some(location) if urls.len() <= self.max_hops => location,
+                _ => {
+                    resp.extensions_mut().insert(RedirectChain { urls });
+                    return Ok(resp);
+                }
+            };
+            req = redirect_request(sent, resp.status(), &url, &location)?;
+            url = location;
+            urls.push(url.clone());
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{Redirect, RedirectChain, RedirectPolicy};
+    use crate::http::{
+        header::{AUTHORIZATION, CONTENT_TYPE, COOKIE, LOCATION},
+        Method, Request, Response, StatusCode,
+    };
+    use crate::middleware::HttpClientExt;
+    use crate::mock::{Client, Error};
+    use crate::HttpClient;
+    use futures::executor::block_on;
+
+    fn request(method: Method, uri: &str) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(CONTENT_TYPE, "text/plain")
+            .header(AUTHORIZATION, "Bearer token")
+            .header(COOKIE, "session=token")
+            .body(b"body".to_vec())?)
+    }
+
+    fn redirect(status: u16, location: &str) -> Result<Response<Vec<u8>>, Error> {
+        Ok(Response::builder()
+            .status(status)
+            .header(LOCATION, location)
+            .body(Vec::new())?)
+    }
+
+    async fn handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        let code = req.uri().path().trim_start_matches('/');
+        match (req.uri().host(), code.parse::<u16>()) {
+            (Some("mock.rs"), Ok(status)) => redirect(status, "/echo"),
+            (_, _) if code == "cross" => redirect(302, "https://other.rs/echo"),
+            (_, _) if code == "loop" => redirect(302, "loop"),
+            _ => {
+                let echo = format!(
+                    "{} {} {} {} {}",
+                    req.method(),
+                    String::from_utf8_lossy(req.body()),
+                    req.headers().contains_key(CONTENT_TYPE),
+                    req.headers().contains_key(AUTHORIZATION),
+                    req.headers().contains_key(COOKIE),
+                );
+                Ok(Response::builder().body(echo.into_bytes())?)
+            }
+        }
+    }
+
+    fn echo(
+        client: &impl HttpClient<Err = Error>,
+        method: Method,
+        uri: &str,
+    ) -> Result<String, Error> {
+        let resp = block_on(client.request(request(method, uri)?))?;
+        Ok(String::from_utf8(resp.into_body()).unwrap())
+    }
+
+    #[test]
+    fn rewrite_method() -> Result<(), Error> {
+        let client =
+            Client::new("https://mock.rs".parse()?, handler).with_middleware(Redirect::new());
+        assert_eq!(
+            "GET  false true true",
+            echo(&client, Method::POST, "https://mock.rs/301")?
+        );
+        assert_eq!(
+            "PUT body true true true",
+            echo(&client, Method::PUT, "https://mock.rs/302")?
+        );
+        assert_eq!(
+            "GET  false true true",
+            echo(&client, Method::PUT, "https://mock.rs/303")?
+        );
+        assert_eq!(
+            "HEAD body true true true",
+            echo(&client, Method::HEAD, "https://mock.rs/303")?
+        );
+        assert_eq!(
+            "POST body true true true",
+            echo(&client, Method::POST, "https://mock.rs/307")?
+        );
+        assert_eq!(
+            "POST body true true true",
+            echo(&client, Method::POST, "https://mock.rs/308")?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cross_origin() -> Result<(), Error> {
+        let client =
+            Client::new("https://mock.rs".parse()?, handler).with_middleware(Redirect::new());
+        assert_eq!(
+            "GET body true false false",
+            echo(&client, Method::GET, "https://mock.rs/cross")?
+        );
+        let client = Client::new("https://mock.rs".parse()?, handler)
+            .with_middleware(Redirect::new().with_policy(RedirectPolicy::SameOrigin));
+        let resp = block_on(client.request(request(Method::GET, "https://mock.rs/cross")?))?;
+        assert_eq!(StatusCode::FOUND, resp.status());
+        Ok(())
+    }
+
+    #[test]
+    fn chain() -> Result<(), Error> {
+        let client =
+            Client::new("https://mock.rs".parse()?, handler).with_middleware(Redirect::new());
+        let resp = block_on(client.request(request(Method::GET, "https://mock.rs/cross")?))?;
+        let chain = resp.extensions().get::<RedirectChain>().unwrap();
+        assert_eq!("https://mock.rs/cross", chain.original_url().as_str());
+        assert_eq!("https://other.rs/echo", chain.final_url().as_str());
+        assert_eq!(1, chain.hops());
+        let resp = block_on(client.request(request(Method::GET, "https://mock.rs/echo")?))?;
+        assert_eq!(0, resp.extensions().get::<RedirectChain>().unwrap().hops());
+        Ok(())
+    }
+
+    #[test]
+    fn max_hops() -> Result<(), Error> {
+        let client = Client::new("https://mock.rs".parse()?, handler)
+            .with_middleware(Redirect::new().with_max_hops(3));
+        let resp = block_on(client.request(request(Method::GET, "https://mock.rs/loop")?))?;
+        assert_eq!(StatusCode::FOUND, resp.status());
+        assert_eq!(3, resp.extensions().get::<RedirectChain>().unwrap().hops());
+        Ok(())
+    }
+}
@@ -0,0 +1,316 @@
+use super::{clone_request, Middleware};
+use crate::{
+    async_trait,
+    http::{header::RETRY_AFTER, Method, Request, Response, StatusCode},
+    HttpClient, RequestOptions,
+};
+use futures_timer::Delay;
+use rand::Rng;
+use std::time::{Duration, SystemTime};
+
+const DEFAULT_MAX_ATTEMPTS: usize = 3;
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(100);
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(10);
+const DEFAULT_STATUSES: [StatusCode; 4] = [
+    StatusCode::TOO_MANY_REQUESTS,
+    StatusCode::BAD_GATEWAY,
+    StatusCode::SERVICE_UNAVAILABLE,
+    StatusCode::GATEWAY_TIMEOUT,
+];
+const IDEMPOTENT_METHODS: [Method; 6] = [
+    Method::GET,
+    Method::HEAD,
+    Method::PUT,
+    Method::DELETE,
+    Method::OPTIONS,
+    Method::TRACE,
+];
+
+/// Middleware retrying failed requests, with exponential backoff and full jitter.
+///
+/// A request is retried when the wrapped client returns an error or a retryable status code,
+/// only if its method is idempotent or the `http_service` method is annotated by `#[retry]`.
+///
+/// If the response has a `Retry-After` header, it will be honored instead of backoff;
+/// the response is returned directly if `Retry-After` exceeds the max delay.
+///
+/// Attempts except the last one are sent by `clone_request`,
+/// they carry no extension but `RequestOptions`.
+///
+/// ```rust
+/// use interfacer_http::middleware::Retry;
+/// use interfacer_http::http::StatusCode;
+/// use std::time::Duration;
+///
+/// let retry = Retry::new()
+///     .with_max_attempts(5)
+///     .with_backoff(Duration::from_millis(50), Duration::from_secs(5))
+///     .with_statuses(vec![StatusCode::SERVICE_UNAVAILABLE]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Retry {
+    max_attempts: usize,
+    base_delay: Duration,
+    max_delay: Duration,
+    statuses: Vec<StatusCode>,
+    retry_after: bool,
+}
+
+impl Retry {
+    /// Construct a default Retry.
+    ///
+    /// At most 3 attempts, backoff from 100ms to 10s,
+    /// retry on 429, 502, 503 and 504, honor `Retry-After`.
+    pub fn new() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+            statuses: DEFAULT_STATUSES.to_vec(),
+            retry_after: true,
+        }
+    }
+}
+
+impl Default for Retry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Retry {
+    /// set max attempts, including the first one.
+    pub fn with_max_attempts(self, max_attempts: usize) -> Self {
+        Self {
+            max_attempts,
+            ..self
+        }
+    }
+
+    /// set base delay and max delay of backoff.
+    pub fn with_backoff(self, base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+            ..self
+        }
+    }
+
+    /// set retryable status codes.
+    pub fn with_statuses(self, statuses: impl IntoIterator<Item = StatusCode>) -> Self {
+        Self {
+            statuses: statuses.into_iter().collect(),
+            ..self
+        }
+    }
+
+    /// honor `Retry-After` or not.
+    pub fn with_retry_after(self, retry_after: bool) -> Self {
+        Self {
+            retry_after,
+            ..self
+        }
+    }
+
+    /// whether the request can be retried.
+    pub fn is_retryable<T>(&self, req: &Request<T>) -> bool {
+        IDEMPOTENT_METHODS.contains(req.method()) || RequestOptions::of(req).retry()
+    }
+
+    /// delay before the next attempt, `attempt` starts from 1.
+    pub fn backoff(&self, attempt: usize) -> Duration {
+        let exp = self
+            .base_delay
+            .checked_mul(1 << (attempt.saturating_sub(1).min(31) as u32))
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay);
+        exp.mul_f64(rand::thread_rng().gen_range(0.0, 1.0))
+    }
+
+    /// delay required by the `Retry-After` header of `resp`.
+    fn retry_after<T>(&self, resp: &Response<T>) -> Option<Duration> {
+        if !self.retry_after {
+            return None;
+        }
+        retry_after(resp)
+    }
+}
+
+/// parse `Retry-After` header, in delay-seconds or HTTP-date.
+pub fn retry_after<T>(resp: &Response<T>) -> Option<Duration> {
+    let value = resp.headers().get(RETRY_AFTER)?.to_str().ok()?.trim();
+    match value.parse::<u64>() {
+        Ok(secs) => Some(Duration::from_secs(secs)),
+        Err(_) => {
+            let date = httpdate::parse_http_date(value).ok()?;
+            Some(
+                date.duration_since(SystemTime::now())
+                    .unwrap_or_else(|_| Duration::from_secs(0)),
+            )
+        }
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for Retry {
+    async fn handle(&self, req: Request<Vec<u8>>, next: &C) -> Result<Response<Vec<u8>>, C::Err> {
+        if self.max_attempts <= 1 || !self.is_retryable(&req) {
+            return next.request(req).await;
+        }
+        let mut req = Some(req);
+        let mut attempt = 0;
+        loop {
+            attempt += 1;
+            let last = attempt >= self.max_attempts;
+            let current = if last {
+                req.take().unwrap()
+            } else {
+                clone_request(req.as_ref().unwrap())
+            };
+            let delay = match next.request(current).await {
+                Ok(resp) if !last && self.statuses.contains(&resp.status()) => {
+                    match self.retry_after(&resp) {
+                        Some(delay) if delay > self.max_delay => return Ok(resp),
+                        Some(delay) => delay,
+                        None => self.backoff(attempt),
+                    }
+                }
+                Err(_) if !last => self.backoff(attempt),
+                result => return result,
+            };
+            Delay::new(delay).await;
+        }
+    }
+}
+
+#[cfg(all(test, feature = "mock"))]
+mod tests {
+    use super::{retry_after, Retry};
+    use crate::http::{header::RETRY_AFTER, Method, Request, Response, StatusCode};
+    use crate::middleware::HttpClientExt;
+    use crate::mock::{Client, Error};
+    use crate::{HttpClient, RequestOptions};
+    use futures::executor::block_on;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    fn retry() -> Retry {
+        Retry::new().with_backoff(Duration::from_millis(1), Duration::from_millis(10))
+    }
+
+    fn request(method: Method) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .method(method)
+            .uri("https://mock.rs")
+            .body(b"body".to_vec())?)
+    }
+
+    #[test]
+    fn backoff() {
+        let retry = Retry::new().with_backoff(Duration::from_millis(100), Duration::from_secs(1));
+        for attempt in 1..100 {
+            let delay = retry.backoff(attempt);
+            assert!(delay <= Duration::from_secs(1));
+            if attempt == 1 {
+                assert!(delay <= Duration::from_millis(100));
+            }
+        }
+    }
+
+    #[test]
+    fn is_retryable() -> Result<(), Error> {
+        let retry = Retry::new();
+        assert!(retry.is_retryable(&request(Method::GET)?));
+        assert!(retry.is_retryable(&request(Method::PUT)?));
+        assert!(!retry.is_retryable(&request(Method::POST)?));
+        let mut req = request(Method::POST)?;
+        req.extensions_mut()
+            .insert(RequestOptions::new().with_retry(true));
+        assert!(retry.is_retryable(&req));
+        Ok(())
+    }
+
+    #[test]
+    fn parse_retry_after() -> Result<(), Error> {
+        let resp = Response::builder().header(RETRY_AFTER, "120").body(())?;
+        assert_eq!(Some(Duration::from_secs(120)), retry_after(&resp));
+        let resp = Response::builder()
+            .header(RETRY_AFTER, "Wed, 21 Oct 2015 07:28:00 GMT")
+            .body(())?;
+        assert_eq!(Some(Duration::from_secs(0)), retry_after(&resp));
+        let resp = Response::builder().header(RETRY_AFTER, "soon").body(())?;
+        assert_eq!(None, retry_after(&resp));
+        Ok(())
+    }
+
+    fn unavailable_twice(req: Request<Vec<u8>>, count: usize) -> Result<Response<Vec<u8>>, Error> {
+        assert_eq!(b"body".to_vec(), req.into_body());
+        let status = match count % 3 {
+            2 => 200,
+            _ => 503,
+        };
+        Ok(Response::builder()
+            .status(status)
+            .header(RETRY_AFTER, "0")
+            .body(Vec::new())?)
+    }
+
+    #[test]
+    fn retry_status() -> Result<(), Error> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let handler = move |req| {
+            let count = counter.fetch_add(1, Ordering::SeqCst);
+            async move { unavailable_twice(req, count) }
+        };
+        let client = Client::new("https://mock.rs".parse()?, handler).with_middleware(retry());
+        let resp = block_on(client.request(request(Method::GET)?))?;
+        assert_eq!(StatusCode::OK, resp.status());
+        assert_eq!(3, calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    fn always_reset(_req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        Err(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset").into())
+    }
+
+    #[test]
+    fn retry_error() -> Result<(), Error> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let handler = move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move { always_reset(req) }
+        };
+        let client = Client::new("https://mock.rs".parse()?, handler)
+            .with_middleware(retry().with_max_attempts(4));
+        assert!(block_on(client.request(request(Method::DELETE)?)).is_err());
+        assert_eq!(4, calls.load(Ordering::SeqCst));
+        assert!(block_on(client.request(request(Method::POST)?)).is_err());
+        assert_eq!(5, calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+
+    fn retry_after_long(_req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        Ok(Response::builder()
+            .status(429)
+            .header(RETRY_AFTER, "3600")
+            .body(Vec::new())?)
+    }
+
+    #[test]
+    fn retry_after_exceeds() -> Result<(), Error> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let handler = move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move { retry_after_long(req) }
+        };
+        let client = Client::new("https://mock.rs".parse()?, handler).with_middleware(retry());
+        let resp = block_on(client.request(request(Method::GET)?))?;
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, resp.status());
+        assert_eq!(1, calls.load(Ordering::SeqCst));
+        Ok(())
+    }
+}
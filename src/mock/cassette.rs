@@ -0,0 +1,571 @@
+use super::error::{Error, Result, Unmatched};
+use crate::middleware::{clone_request, Middleware};
+use crate::{
+    async_trait,
+    http::{
+        header::{HeaderName, AUTHORIZATION, COOKIE, PROXY_AUTHORIZATION, SET_COOKIE},
+        HeaderMap, Request, Response,
+    },
+    url::Url,
+    Helper, HttpClient,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+const REDACTED: &str = "[REDACTED]";
+
+/// Recorded request and response pairs.
+///
+/// Bodies are stored as strings if they are valid utf-8, or as byte arrays.
+///
+/// ```rust
+/// use interfacer_http::mock::{Cassette, CassetteFormat};
+///
+/// let yaml = r#"
+/// interactions:
+///   - request:
+///       method: GET
+///       url: "https://mock.rs/api/user/0"
+///     response:
+///       status: 200
+///       headers:
+///         content-type: ["application/json"]
+///       body: '{"name": "hexi", "age": 20}'
+/// "#;
+/// let cassette = Cassette::read(yaml.as_bytes(), CassetteFormat::Yaml).unwrap();
+/// assert_eq!(200, cassette.interactions[0].response.status);
+/// ```
+#[derive(Debug, Clone, Default, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Cassette {
+    /// interactions in recorded order.
+    pub interactions: Vec<Interaction>,
+}
+
+/// A request and the response to it.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct Interaction {
+    /// the recorded request.
+    pub request: RecordedRequest,
+    /// the recorded response.
+    pub response: RecordedResponse,
+}
+
+/// A request in a `Cassette`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// request method, like `GET`.
+    pub method: String,
+    /// full url, query string included as is; secrets in it are not redacted.
+    pub url: String,
+    /// header values by lowercase name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, Vec<String>>,
+    /// request body, empty if there is none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "payload")]
+    pub body: Vec<u8>,
+}
+
+/// A response in a `Cassette`.
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RecordedResponse {
+    /// status code, like `200`.
+    pub status: u16,
+    /// header values by lowercase name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub headers: BTreeMap<String, Vec<String>>,
+    /// response body, empty if there is none.
+    #[serde(default, skip_serializing_if = "Vec::is_empty", with = "payload")]
+    pub body: Vec<u8>,
+}
+
+/// File format of `Cassette`.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum CassetteFormat {
+    /// JSON, by `serde_json`.
+    Json,
+    /// YAML, by `serde_yaml`.
+    Yaml,
+}
+
+impl CassetteFormat {
+    /// `Yaml` for `.yaml` or `.yml` files, `Json` for others.
+    pub fn of(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("yaml") | Some("yml") => CassetteFormat::Yaml,
+            _ => CassetteFormat::Json,
+        }
+    }
+}
+
+impl Cassette {
+    /// Construct an empty Cassette.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// load a cassette file, format is detected by `CassetteFormat::of`.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        Self::read(BufReader::new(File::open(path)?), CassetteFormat::of(path))
+    }
+
+    /// save as a cassette file, format is detected by `CassetteFormat::of`.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write(&mut writer, CassetteFormat::of(path))?;
+        writer.flush()
+    }
+
+    /// read a cassette in `format`.
+    pub fn read(reader: impl Read, format: CassetteFormat) -> io::Result<Self> {
+        match format {
+            CassetteFormat::Json => serde_json::from_reader(reader).map_err(invalid_data),
+            CassetteFormat::Yaml => serde_yaml::from_reader(reader).map_err(invalid_data),
+        }
+    }
+
+    /// write the cassette in `format`.
+    pub fn write(&self, writer: impl Write, format: CassetteFormat) -> io::Result<()> {
+        match format {
+            CassetteFormat::Json => {
+                serde_json::to_writer_pretty(writer, self).map_err(invalid_data)
+            }
+            CassetteFormat::Yaml => serde_yaml::to_writer(writer, self).map_err(invalid_data),
+        }
+    }
+}
+
+fn invalid_data(err: impl std::error::Error + Send + Sync + 'static) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, err)
+}
+
+fn record_headers(headers: &HeaderMap, redacted: &[HeaderName]) -> BTreeMap<String, Vec<String>> {
+    let mut recorded = BTreeMap::<String, Vec<String>>::new();
+    for (name, value) in headers.iter() {
+        let value = if redacted.contains(name) {
+            REDACTED.to_owned()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        recorded
+            .entry(name.as_str().to_owned())
+            .or_default()
+            .push(value);
+    }
+    recorded
+}
+
+impl Interaction {
+    fn record(req: &Request<Vec<u8>>, resp: &Response<Vec<u8>>, redacted: &[HeaderName]) -> Self {
+        Self {
+            request: RecordedRequest {
+                method: req.method().to_string(),
+                url: req.uri().to_string(),
+                headers: record_headers(req.headers(), redacted),
+                body: req.body().clone(),
+            },
+            response: RecordedResponse {
+                status: resp.status().as_u16(),
+                headers: record_headers(resp.headers(), redacted),
+                body: resp.body().clone(),
+            },
+        }
+    }
+}
+
+impl RecordedRequest {
+    /// differences between `req` and the recorded one, empty if they match.
+    fn diff(&self, req: &Request<Vec<u8>>, headers: &[HeaderName], body: bool) -> Vec<String> {
+        let mut diffs = Vec::new();
+        if req.method().as_str() != self.method {
+            diffs.push(format!(
+                "method: `{}` != recorded `{}`",
+                req.method(),
+                self.method
+            ));
+        }
+        let url = req.uri().to_string();
+        let same_url = match (url.parse::<Url>(), self.url.parse::<Url>()) {
+            (Ok(actual), Ok(recorded)) => actual == recorded,
+            _ => url == self.url,
+        };
+        if !same_url {
+            diffs.push(format!("url: `{}` != recorded `{}`", url, self.url));
+        }
+        let actual_headers = record_headers(req.headers(), &[]);
+        let empty = Vec::new();
+        for name in headers {
+            let actual = actual_headers.get(name.as_str()).unwrap_or(&empty);
+            let recorded = self.headers.get(name.as_str()).unwrap_or(&empty);
+            // redacted values are matched by presence.
+            let redacted = !recorded.is_empty() && recorded.iter().all(|value| value == REDACTED);
+            let matched = if redacted {
+                actual.len() == recorded.len()
+            } else {
+                actual == recorded
+            };
+            if !matched {
+                diffs.push(format!(
+                    "header {}: {:?} != recorded {:?}",
+                    name, actual, recorded
+                ));
+            }
+        }
+        if body && req.body() != &self.body {
+            diffs.push(format!(
+                "body: {:?} != recorded {:?}",
+                String::from_utf8_lossy(req.body()),
+                String::from_utf8_lossy(&self.body)
+            ));
+        }
+        diffs
+    }
+}
+
+impl RecordedResponse {
+    fn to_response(&self) -> Result<Response<Vec<u8>>> {
+        let mut builder = Response::builder();
+        builder.status(self.status);
+        for (name, values) in self.headers.iter() {
+            for value in values {
+                builder.header(name.as_str(), value.as_str());
+            }
+        }
+        Ok(builder.body(self.body.clone())?)
+    }
+}
+
+/// Middleware recording requests and responses into a `Cassette`.
+///
+/// Values of `Authorization`, `Proxy-Authorization` and `Cookie` in requests
+/// and `Set-Cookie` in responses are redacted by default.
+/// Urls are recorded as is, so keep secrets out of query strings or edit the cassette.
+/// `Recorder` is a shared handle, clone it to save the cassette after it wraps a client.
+///
+/// ```rust,ignore
+/// use interfacer_http::middleware::HttpClientExt;
+/// use interfacer_http::mock::Recorder;
+///
+/// let recorder = Recorder::new();
+/// let service = client.with_middleware(recorder.clone());
+/// service.get_user(0).await?;
+/// recorder.save("tests/cassettes/get_user.yaml")?;
+/// ```
+#[derive(Debug, Clone)]
+pub struct Recorder {
+    cassette: Arc<Mutex<Cassette>>,
+    redacted: Vec<HeaderName>,
+}
+
+impl Recorder {
+    /// Construct a Recorder with an empty cassette.
+    pub fn new() -> Self {
+        Self {
+            cassette: Arc::new(Mutex::new(Cassette::new())),
+            redacted: vec![AUTHORIZATION, PROXY_AUTHORIZATION, COOKIE, SET_COOKIE],
+        }
+    }
+}
+
+impl Default for Recorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Recorder {
+    /// set headers whose values are redacted, in both requests and responses.
+    pub fn with_redacted_headers(self, redacted: Vec<HeaderName>) -> Self {
+        Self { redacted, ..self }
+    }
+
+    /// recorded interactions.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    /// save recorded interactions as a cassette file.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        self.cassette().save(path)
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> Middleware<C> for Recorder {
+    async fn handle(
+        &self,
+        req: Request<Vec<u8>>,
+        next: &C,
+    ) -> std::result::Result<Response<Vec<u8>>, C::Err> {
+        let recorded = clone_request(&req);
+        let resp = next.request(req).await?;
+        let interaction = Interaction::record(&recorded, &resp, &self.redacted);
+        self.cassette.lock().unwrap().interactions.push(interaction);
+        Ok(resp)
+    }
+}
+
+/// A mock server replaying a `Cassette`.
+///
+/// Requests are matched by method, url, selected headers and body;
+/// interactions are served in order, then reused if all the matched ones are served.
+/// Unmatched requests fail by `Error::Unmatched`, with differences to the closest interaction.
+///
+/// ```rust,ignore
+/// use interfacer_http::mock::{Cassette, Replay};
+///
+/// let cassette = Cassette::load("tests/cassettes/get_user.yaml")?;
+/// let service = Replay::new("https://mock.rs".parse()?, cassette);
+/// let user = service.get_user(0).await?;
+/// ```
+pub struct Replay {
+    helper: Helper,
+    interactions: Vec<Interaction>,
+    served: Mutex<Vec<bool>>,
+    match_headers: Vec<HeaderName>,
+    match_body: bool,
+}
+
+impl Replay {
+    /// @param base_url: base url to join path
+    /// @param cassette: recorded interactions
+    pub fn new(base_url: Url, cassette: Cassette) -> Self {
+        let served = vec![false; cassette.interactions.len()];
+        Self {
+            helper: Helper::new().with_base_url(base_url),
+            interactions: cassette.interactions,
+            served: Mutex::new(served),
+            match_headers: Vec::new(),
+            match_body: true,
+        }
+    }
+
+    /// replace the helper, base url should be bound to `helper` again.
+    pub fn with_helper(self, helper: Helper) -> Self {
+        Self { helper, ..self }
+    }
+
+    /// match requests by these headers, none by default.
+    ///
+    /// redacted headers are matched by presence, as their recorded values are `[REDACTED]`.
+    pub fn with_match_headers(self, match_headers: Vec<HeaderName>) -> Self {
+        Self {
+            match_headers,
+            ..self
+        }
+    }
+
+    /// match requests by body or not, true by default.
+    pub fn with_match_body(self, match_body: bool) -> Self {
+        Self { match_body, ..self }
+    }
+
+    fn unmatched(&self, req: &Request<Vec<u8>>) -> Unmatched {
+        let closest = self
+            .interactions
+            .iter()
+            .map(|interaction| {
+                let diffs = interaction
+                    .request
+                    .diff(req, &self.match_headers, self.match_body);
+                (interaction, diffs)
+            })
+            .min_by_key(|(_, diffs)| diffs.len());
+        let reasons = match closest {
+            Some((interaction, diffs)) => {
+                let mut reasons = vec![format!(
+                    "closest interaction: `{} {}`",
+                    interaction.request.method, interaction.request.url
+                )];
+                reasons.extend(diffs);
+                reasons
+            }
+            None => vec!["cassette is empty".to_owned()],
+        };
+        Unmatched {
+            request: format!("{} {}", req.method(), req.uri()),
+            reasons,
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for Replay {
+    type Err = Error;
+    async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        let matched = self
+            .interactions
+            .iter()
+            .enumerate()
+            .filter(|(_, interaction)| {
+                interaction
+                    .request
+                    .diff(&req, &self.match_headers, self.match_body)
+                    .is_empty()
+            })
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let index = {
+            let mut served = self.served.lock().unwrap();
+            let index = matched
+                .iter()
+                .cloned()
+                .find(|index| !served[*index])
+                .or_else(|| matched.first().cloned());
+            if let Some(index) = index {
+                served[index] = true;
+            }
+            index
+        };
+        match index {
+            Some(index) => self.interactions[index].response.to_response(),
+            None => Err(self.unmatched(&req).into()),
+        }
+    }
+
+    fn helper(&self) -> &Helper {
+        &self.helper
+    }
+}
+
+/// serialize bodies as strings if they are valid utf-8, or as byte arrays.
+mod payload {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Payload {
+        Text(String),
+        Binary(Vec<u8>),
+    }
+
+    pub fn serialize<S: Serializer>(body: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(body) {
+            Ok(text) => serializer.serialize_str(text),
+            Err(_) => serializer.collect_seq(body),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        Ok(match Payload::deserialize(deserializer)? {
+            Payload::Text(text) => text.into_bytes(),
+            Payload::Binary(data) => data,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cassette, CassetteFormat, Recorder, Replay};
+    use crate::http::{
+        header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, SET_COOKIE},
+        Method, Request, Response,
+    };
+    use crate::middleware::HttpClientExt;
+    use crate::mock::{Client, Error};
+    use crate::HttpClient;
+    use futures::executor::block_on;
+
+    fn request(method: Method, uri: &str, body: &[u8]) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(ACCEPT, "application/json")
+            .header(AUTHORIZATION, "Bearer token")
+            .body(body.to_vec())?)
+    }
+
+    async fn handler(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        let body = match req.uri().path() {
+            "/binary" => vec![0xff, 0x00, 0xfe],
+            _ => format!("{} {}", req.method(), String::from_utf8_lossy(req.body())).into_bytes(),
+        };
+        Ok(Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, "text/plain")
+            .header(SET_COOKIE, "session=token; HttpOnly")
+            .body(body)?)
+    }
+
+    fn record() -> Result<Cassette, Error> {
+        let recorder = Recorder::new();
+        let client =
+            Client::new("https://mock.rs".parse()?, handler).with_middleware(recorder.clone());
+        block_on(client.request(request(Method::GET, "https://mock.rs/user?id=0", b"")?))?;
+        block_on(client.request(request(Method::POST, "https://mock.rs/user", b"hexi")?))?;
+        block_on(client.request(request(Method::GET, "https://mock.rs/binary", b"")?))?;
+        Ok(recorder.cassette())
+    }
+
+    #[test]
+    fn record_and_redact() -> Result<(), Error> {
+        let cassette = record()?;
+        assert_eq!(3, cassette.interactions.len());
+        let post = &cassette.interactions[1];
+        assert_eq!("POST", post.request.method);
+        assert_eq!(b"hexi".to_vec(), post.request.body);
+        assert_eq!(vec!["[REDACTED]"], post.request.headers["authorization"]);
+        assert_eq!(b"POST hexi".to_vec(), post.response.body);
+        assert_eq!(vec!["text/plain"], post.response.headers["content-type"]);
+        assert_eq!(vec!["[REDACTED]"], post.response.headers["set-cookie"]);
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> Result<(), Error> {
+        let cassette = record()?;
+        for format in &[CassetteFormat::Json, CassetteFormat::Yaml] {
+            let mut data = Vec::new();
+            cassette.write(&mut data, *format)?;
+            assert_eq!(cassette, Cassette::read(data.as_slice(), *format)?);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn replay() -> Result<(), Error> {
+        let client = Replay::new("https://mock.rs".parse()?, record()?);
+        let resp =
+            block_on(client.request(request(Method::POST, "https://mock.rs/user", b"hexi")?))?;
+        assert_eq!(200, resp.status());
+        assert_eq!("text/plain", resp.headers()[CONTENT_TYPE]);
+        assert_eq!(b"POST hexi".to_vec(), resp.into_body());
+        let resp = block_on(client.request(request(Method::GET, "https://mock.rs/binary", b"")?))?;
+        assert_eq!(vec![0xff, 0x00, 0xfe], resp.into_body());
+        // served interactions are reused.
+        block_on(client.request(request(Method::GET, "https://mock.rs/binary", b"")?))?;
+        Ok(())
+    }
+
+    #[test]
+    fn unmatched() -> Result<(), Error> {
+        let client = Replay::new("https://mock.rs".parse()?, record()?);
+        match block_on(client.request(request(Method::POST, "https://mock.rs/user", b"hexilee")?)) {
+            Err(Error::Unmatched(unmatched)) => {
+                assert_eq!("POST https://mock.rs/user", unmatched.request);
+                assert_eq!(2, unmatched.reasons.len());
+                assert!(unmatched.reasons[1].starts_with("body:"));
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let client = Replay::new("https://mock.rs".parse()?, record()?)
+            .with_match_headers(vec![AUTHORIZATION]);
+        block_on(client.request(request(Method::GET, "https://mock.rs/user?id=0", b"")?))?;
+        let mut req = request(Method::GET, "https://mock.rs/user?id=0", b"")?;
+        req.headers_mut().remove(AUTHORIZATION);
+        match block_on(client.request(req)) {
+            Err(Error::Unmatched(unmatched)) => {
+                assert!(unmatched.to_string().contains("header authorization"))
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let client = Replay::new("https://mock.rs".parse()?, Cassette::new());
+        assert!(block_on(client.request(request(Method::GET, "https://mock.rs", b"")?)).is_err());
+        Ok(())
+    }
+}
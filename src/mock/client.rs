@@ -8,28 +8,52 @@ use crate::{
 use std::future::Future;
 
 /// A mock server
-pub struct Client<F> {
+///
+/// The handler can be a function or a closure capturing state,
+/// like a call counter or an in-memory store shared by `Arc`.
+///
+/// ```rust
+/// use interfacer_http::http::{Request, Response};
+/// use interfacer_http::mock::Client;
+/// use std::sync::atomic::{AtomicUsize, Ordering};
+/// use std::sync::Arc;
+///
+/// let calls = Arc::new(AtomicUsize::new(0));
+/// let counter = calls.clone();
+/// let client = Client::new("http://localhost".parse().unwrap(), move |_: Request<Vec<u8>>| {
+///     counter.fetch_add(1, Ordering::SeqCst);
+///     async { Ok(Response::new(Vec::new())) }
+/// });
+/// ```
+pub struct Client<H> {
     helper: Helper,
-    handler: fn(Request<Vec<u8>>) -> F,
+    handler: H,
 }
 
-impl<F> Client<F>
+impl<H, F> Client<H>
 where
+    H: Fn(Request<Vec<u8>>) -> F + Send + Sync,
     F: Future<Output = Result<Response<Vec<u8>>>> + Send + 'static,
 {
     /// @param base_url: base url to join path
     /// @param handler: mock handler implementation
-    pub fn new(base_url: Url, handler: fn(Request<Vec<u8>>) -> F) -> Self {
+    pub fn new(base_url: Url, handler: H) -> Self {
         Self {
             handler,
             helper: Helper::new().with_base_url(base_url),
         }
     }
+
+    /// replace the helper, base url should be bound to `helper` again.
+    pub fn with_helper(self, helper: Helper) -> Self {
+        Self { helper, ..self }
+    }
 }
 
 #[async_trait]
-impl<F> HttpClient for Client<F>
+impl<H, F> HttpClient for Client<H>
 where
+    H: Fn(Request<Vec<u8>>) -> F + Send + Sync,
     F: Future<Output = Result<Response<Vec<u8>>>> + Send + 'static,
 {
     type Err = Error;
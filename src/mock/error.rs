@@ -1,5 +1,8 @@
-use crate::{http, url, FromContentError, ToContentError, Unexpected};
+use crate::{
+    http, url, AuthError, FromContentError, PathSegmentError, Timeout, ToContentError, Unexpected,
+};
 use derive_more::{Display, From};
+use std::fmt::{self, Formatter};
 
 /// Error for mock server
 #[allow(clippy::large_enum_variant)]
@@ -19,9 +22,43 @@ pub enum Error {
 
     #[display(fmt = "{}", _0)]
     Unexpected(Unexpected),
+
+    #[display(fmt = "{}", _0)]
+    PathSegmentError(PathSegmentError),
+
+    #[display(fmt = "io error: {}", _0)]
+    IoError(std::io::Error),
+
+    #[display(fmt = "{}", _0)]
+    Timeout(Timeout),
+
+    #[display(fmt = "{}", _0)]
+    AuthError(AuthError),
+
+    #[display(fmt = "{}", _0)]
+    Unmatched(Unmatched),
 }
 
 impl std::error::Error for Error {}
 
+/// Request matching nothing expected by a mock server.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Unmatched {
+    /// `<method> <url>` of the request.
+    pub request: String,
+    /// why the request is unmatched, like differences to the closest expectation.
+    pub reasons: Vec<String>,
+}
+
+impl fmt::Display for Unmatched {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "unmatched request `{}`", self.request)?;
+        for reason in self.reasons.iter() {
+            write!(f, "\n  {}", reason)?;
+        }
+        Ok(())
+    }
+}
+
 /// alias with `Error`
 pub type Result<T> = std::result::Result<T, Error>;
@@ -0,0 +1,293 @@
+use super::router::match_path;
+use crate::{
+    async_trait,
+    http::{header::CONTENT_TYPE, HeaderValue, Method, Request, Response, StatusCode},
+    mime::Mime,
+    Helper, HttpClient,
+};
+use futures_timer::Delay;
+use std::io;
+use std::ops::{Bound, RangeBounds};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+/// Fault injected by `FaultInjector`.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Fault {
+    /// delay the request before sending it.
+    Latency(Duration),
+    /// drop the connection, fail with `io::ErrorKind::ConnectionReset` without sending the request.
+    Disconnect,
+    /// respond the status code and an empty body without sending the request.
+    Status(StatusCode),
+    /// truncate the response body to at most n bytes, `Content-Length` is kept.
+    Truncate(usize),
+    /// replace `Content-Type` of the response.
+    ContentType(Mime),
+}
+
+/// A fault injected into requests matching a route, on some calls of them.
+#[derive(Debug)]
+pub struct Injection {
+    fault: Fault,
+    route: Option<(Method, String)>,
+    calls: (usize, Option<usize>),
+    count: AtomicUsize,
+}
+
+impl Injection {
+    /// Construct a default Injection.
+    ///
+    /// Inject `fault` into every call of every request.
+    pub fn new(fault: Fault) -> Self {
+        Self {
+            fault,
+            route: None,
+            calls: (1, None),
+            count: AtomicUsize::new(0),
+        }
+    }
+
+    /// only inject into requests matching the method and the path pattern,
+    /// `{name}` matches a segment, a trailing `*` matches the rest.
+    pub fn with_route(self, method: Method, path: impl Into<String>) -> Self {
+        Self {
+            route: Some((method, path.into())),
+            ..self
+        }
+    }
+
+    /// only inject into a range of calls of matched requests, counted from 1,
+    /// like `3..=3` for the third call and `..3` for the first two calls.
+    pub fn with_calls(self, calls: impl RangeBounds<usize>) -> Self {
+        let start = match calls.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start.saturating_add(1),
+            Bound::Unbounded => 1,
+        };
+        let end = match calls.end_bound() {
+            // `..=usize::MAX` is unbounded.
+            Bound::Included(end) => end.checked_add(1),
+            Bound::Excluded(end) => Some(*end),
+            Bound::Unbounded => None,
+        };
+        Self {
+            calls: (start, end),
+            ..self
+        }
+    }
+
+    /// the fault if it should be injected into `req`, count matched requests.
+    fn inject<T>(&self, req: &Request<T>) -> Option<&Fault> {
+        if let Some((method, path)) = self.route.as_ref() {
+            if method != req.method() || match_path(path, req.uri().path()).is_none() {
+                return None;
+            }
+        }
+        let call = self.count.fetch_add(1, Ordering::SeqCst) + 1;
+        match self.calls {
+            (start, _) if call < start => None,
+            (_, Some(end)) if call >= end => None,
+            _ => Some(&self.fault),
+        }
+    }
+}
+
+/// A wrapper of HttpClient injecting faults, to test retry and error handling.
+///
+/// All injections matching a request take effect in order of addition;
+/// `Disconnect` and `Status` stop the request, later injections are skipped.
+///
+/// ```rust
+/// use interfacer_http::http::{Method, Request, Response, StatusCode};
+/// use interfacer_http::mock::{Client, Fault, FaultInjector, Injection};
+/// use std::time::Duration;
+///
+/// let client = Client::new("http://localhost".parse().unwrap(), |_: Request<Vec<u8>>| {
+///     async { Ok(Response::new(Vec::new())) }
+/// });
+/// let client = FaultInjector::new(client)
+///     .with_injection(
+///         Injection::new(Fault::Status(StatusCode::SERVICE_UNAVAILABLE))
+///             .with_route(Method::GET, "/api/user/{id}")
+///             .with_calls(1..3),
+///     )
+///     .with_injection(Injection::new(Fault::Latency(Duration::from_millis(10))));
+/// ```
+pub struct FaultInjector<C> {
+    inner: C,
+    injections: Vec<Injection>,
+}
+
+impl<C> FaultInjector<C> {
+    /// Construct a default FaultInjector.
+    ///
+    /// Without any injection.
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            injections: Vec::new(),
+        }
+    }
+
+    /// add an injection.
+    pub fn with_injection(mut self, injection: Injection) -> Self {
+        self.injections.push(injection);
+        self
+    }
+}
+
+#[async_trait]
+impl<C: HttpClient> HttpClient for FaultInjector<C> {
+    type Err = C::Err;
+    async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Self::Err> {
+        let faults = self
+            .injections
+            .iter()
+            .filter_map(|injection| injection.inject(&req))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut after = Vec::new();
+        for fault in faults {
+            match fault {
+                Fault::Latency(duration) => Delay::new(duration).await,
+                Fault::Disconnect => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::ConnectionReset,
+                        "connection reset by fault injection",
+                    )
+                    .into())
+                }
+                Fault::Status(status) => {
+                    return Ok(Response::builder().status(status).body(Vec::new())?)
+                }
+                fault => after.push(fault),
+            }
+        }
+        let mut resp = self.inner.request(req).await?;
+        for fault in after {
+            match fault {
+                Fault::Truncate(size) => resp.body_mut().truncate(size),
+                Fault::ContentType(mime) => {
+                    let content_type =
+                        HeaderValue::from_str(mime.as_ref()).map_err(crate::http::Error::from)?;
+                    resp.headers_mut().insert(CONTENT_TYPE, content_type);
+                }
+                _ => (),
+            }
+        }
+        Ok(resp)
+    }
+
+    fn helper(&self) -> &Helper {
+        self.inner.helper()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Fault, FaultInjector, Injection};
+    use crate::http::{header::CONTENT_TYPE, Method, Request, Response, StatusCode};
+    use crate::mime;
+    use crate::mock::{Client, Error};
+    use crate::HttpClient;
+    use futures::executor::block_on;
+    use std::io;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    fn echo(req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>, Error> {
+        Ok(Response::builder()
+            .status(200)
+            .header(CONTENT_TYPE, "application/json")
+            .body(req.into_body())?)
+    }
+
+    fn request(method: Method, path: &str) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .method(method)
+            .uri(format!("http://localhost{}", path))
+            .body(b"{\"name\": \"hexi\"}".to_vec())?)
+    }
+
+    #[test]
+    fn inject() -> Result<(), Error> {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let handler = move |req| {
+            counter.fetch_add(1, Ordering::SeqCst);
+            async move { echo(req) }
+        };
+        let client = FaultInjector::new(Client::new("http://localhost".parse()?, handler))
+            .with_injection(
+                Injection::new(Fault::Status(StatusCode::SERVICE_UNAVAILABLE))
+                    .with_route(Method::GET, "/api/user/{id}")
+                    .with_calls(..2),
+            )
+            .with_injection(
+                Injection::new(Fault::Disconnect)
+                    .with_route(Method::GET, "/api/user/{id}")
+                    .with_calls(2..=2),
+            )
+            .with_injection(
+                Injection::new(Fault::Truncate(4)).with_route(Method::PUT, "/api/user/{id}"),
+            )
+            .with_injection(
+                Injection::new(Fault::ContentType(mime::TEXT_PLAIN))
+                    .with_route(Method::PUT, "/api/user/{id}")
+                    .with_calls(2..),
+            );
+        block_on(async {
+            let resp = client.request(request(Method::GET, "/api/user/0")?).await?;
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status());
+            assert!(resp.body().is_empty());
+            match client.request(request(Method::GET, "/api/user/0")?).await {
+                Err(Error::IoError(err)) => assert_eq!(io::ErrorKind::ConnectionReset, err.kind()),
+                _ => panic!("expect io error"),
+            }
+            assert_eq!(0, calls.load(Ordering::SeqCst));
+            let resp = client.request(request(Method::GET, "/api/user/0")?).await?;
+            assert_eq!(200, resp.status());
+            assert_eq!(1, calls.load(Ordering::SeqCst));
+
+            let resp = client.request(request(Method::PUT, "/api/user/0")?).await?;
+            assert_eq!(b"{\"na", resp.body().as_slice());
+            assert_eq!(
+                "application/json",
+                resp.headers().get(CONTENT_TYPE).unwrap()
+            );
+            let resp = client.request(request(Method::PUT, "/api/user/0")?).await?;
+            assert_eq!("text/plain", resp.headers().get(CONTENT_TYPE).unwrap());
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn unbounded_calls() -> Result<(), Error> {
+        let client =
+            FaultInjector::new(Client::new("http://localhost".parse()?, |req| async move {
+                echo(req)
+            }))
+            .with_injection(
+                Injection::new(Fault::Status(StatusCode::SERVICE_UNAVAILABLE))
+                    .with_calls(..=usize::MAX),
+            );
+        let resp = block_on(client.request(request(Method::GET, "/api/user/0")?))?;
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, resp.status());
+        Ok(())
+    }
+
+    #[test]
+    fn latency() -> Result<(), Error> {
+        let client = Client::new("http://localhost".parse()?, |_: Request<Vec<u8>>| async {
+            Ok(Response::new(Vec::new()))
+        });
+        let client = FaultInjector::new(client)
+            .with_injection(Injection::new(Fault::Latency(Duration::from_millis(50))));
+        let start = Instant::now();
+        block_on(client.request(request(Method::GET, "/api/user/0")?))?;
+        assert!(start.elapsed() >= Duration::from_millis(50));
+        Ok(())
+    }
+}
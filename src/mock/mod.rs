@@ -2,10 +2,34 @@
 //! need `mock` feature.
 
 #[doc(inline)]
-pub use error::{Error, Result};
+pub use error::{Error, Result, Unmatched};
 
 #[doc(inline)]
 pub use client::Client;
 
+#[doc(inline)]
+#[cfg(feature = "cassette")]
+pub use cassette::{
+    Cassette, CassetteFormat, Interaction, RecordedRequest, RecordedResponse, Recorder, Replay,
+};
+
+#[doc(inline)]
+pub use fault::{Fault, FaultInjector, Injection};
+
+#[doc(inline)]
+pub use multipart::parse_multipart;
+
+#[doc(inline)]
+pub use router::{Params, Router};
+
+#[doc(inline)]
+pub use server::{Expectation, MockServer, Stub};
+
+#[cfg(feature = "cassette")]
+mod cassette;
 mod client;
 mod error;
+mod fault;
+mod multipart;
+mod router;
+mod server;
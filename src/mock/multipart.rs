@@ -0,0 +1,30 @@
+use super::error::Result;
+use crate::http::{header::CONTENT_TYPE, Request};
+use crate::mime::{Mime, APPLICATION_OCTET_STREAM};
+use crate::{ContentInto, Multipart};
+
+/// parse `multipart/form-data` body of a request, to assert uploads in mock handlers.
+///
+/// ```rust
+/// use interfacer_http::mock::parse_multipart;
+/// use interfacer_http::http::{header::CONTENT_TYPE, Request};
+/// use interfacer_http::{mime, Multipart, ToContent};
+///
+/// let form = Multipart::new().with_text("name", "hexi");
+/// let content_type = form.to_content_type(&mime::MULTIPART_FORM_DATA);
+/// let req = Request::builder()
+///     .header(CONTENT_TYPE, content_type.as_ref())
+///     .body(form.to_content(&content_type).unwrap())
+///     .unwrap();
+/// let parsed = parse_multipart(&req).unwrap();
+/// assert_eq!(Some("hexi"), parsed.part("name").and_then(|part| part.as_text()));
+/// ```
+pub fn parse_multipart(req: &Request<Vec<u8>>) -> Result<Multipart> {
+    let content_type: Mime = req
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(APPLICATION_OCTET_STREAM);
+    Ok(req.body().clone().content_into(&content_type)?)
+}
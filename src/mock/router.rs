@@ -0,0 +1,269 @@
+use super::error::Result;
+use crate::http::{header::ALLOW, Method, Request, Response, StatusCode};
+use futures::future::{self, BoxFuture, FutureExt};
+use percent_encoding::percent_decode_str;
+use std::future::Future;
+use std::sync::Arc;
+
+type Handler =
+    dyn Fn(Request<Vec<u8>>) -> BoxFuture<'static, Result<Response<Vec<u8>>>> + Send + Sync;
+
+/// A small in-memory router to emulate a resource in `mock::Client`.
+///
+/// Paths of routes are patterns, `{name}` matches a segment and a trailing `*` matches the rest.
+/// Handlers get captured segments by `Params::of`,
+/// they can share state like an in-memory store by capturing an `Arc`.
+///
+/// Requests matching no route get `404 Not Found`,
+/// requests matching only paths of routes get `405 Method Not Allowed`.
+///
+/// ```rust
+/// use interfacer_http::http::{Method, Request, Response};
+/// use interfacer_http::mock::{Client, Params, Router};
+/// use std::collections::HashMap;
+/// use std::sync::{Arc, Mutex};
+///
+/// let store = Arc::new(Mutex::new(HashMap::<String, Vec<u8>>::new()));
+/// let (get_store, put_store) = (store.clone(), store.clone());
+/// let router = Router::new()
+///     .with_route(Method::GET, "/api/user/{id}", move |req: Request<Vec<u8>>| {
+///         let user = get_store.lock().unwrap().get(Params::of(&req).get("id").unwrap()).cloned();
+///         async move {
+///             Ok(match user {
+///                 Some(user) => Response::new(user),
+///                 None => Response::builder().status(404).body(Vec::new())?,
+///             })
+///         }
+///     })
+///     .with_route(Method::PUT, "/api/user/{id}", move |req: Request<Vec<u8>>| {
+///         let id = Params::of(&req).get("id").unwrap().to_string();
+///         put_store.lock().unwrap().insert(id, req.into_body());
+///         async { Ok(Response::new(Vec::new())) }
+///     });
+/// let client = Client::new("http://localhost".parse().unwrap(), move |req| router.handle(req));
+/// ```
+#[derive(Clone, Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+#[derive(Clone)]
+struct Route {
+    method: Method,
+    path: String,
+    handler: Arc<Handler>,
+}
+
+/// Segments captured by `{name}` in the path of a route.
+///
+/// `Router` attaches them to the extensions of the request before calling the handler.
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Params(Vec<(String, String)>);
+
+impl Params {
+    /// params attached to a request, or empty params.
+    pub fn of<T>(req: &Request<T>) -> Self {
+        req.extensions().get::<Self>().cloned().unwrap_or_default()
+    }
+
+    /// percent-decoded segment captured by `{name}`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl Router {
+    /// Construct a default Router.
+    ///
+    /// Without any route.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// add a route, routes are matched in order of addition.
+    pub fn with_route<H, F>(mut self, method: Method, path: impl Into<String>, handler: H) -> Self
+    where
+        H: Fn(Request<Vec<u8>>) -> F + Send + Sync + 'static,
+        F: Future<Output = Result<Response<Vec<u8>>>> + Send + 'static,
+    {
+        self.routes.push(Route {
+            method,
+            path: path.into(),
+            handler: Arc::new(move |req| handler(req).boxed()),
+        });
+        self
+    }
+
+    /// dispatch a request to the first matched route.
+    pub fn handle(&self, req: Request<Vec<u8>>) -> BoxFuture<'static, Result<Response<Vec<u8>>>> {
+        let mut req = req;
+        let mut allowed = Vec::new();
+        for route in self.routes.iter() {
+            if let Some(params) = match_path(&route.path, req.uri().path()) {
+                if route.method == req.method() {
+                    req.extensions_mut().insert(params);
+                    return (route.handler)(req);
+                }
+                if !allowed.contains(&route.method) {
+                    allowed.push(route.method.clone());
+                }
+            }
+        }
+        future::ready(fallback(allowed)).boxed()
+    }
+}
+
+fn fallback(allowed: Vec<Method>) -> Result<Response<Vec<u8>>> {
+    if allowed.is_empty() {
+        return Ok(Response::builder()
+            .status(StatusCode::NOT_FOUND)
+            .body(Vec::new())?);
+    }
+    let allowed = allowed
+        .iter()
+        .map(Method::as_str)
+        .collect::<Vec<_>>()
+        .join(", ");
+    Ok(Response::builder()
+        .status(StatusCode::METHOD_NOT_ALLOWED)
+        .header(ALLOW, allowed.as_str())
+        .body(Vec::new())?)
+}
+
+/// match a path against a pattern, `{name}` matches a segment, a trailing `*` matches the rest.
+pub(crate) fn match_path(pattern: &str, path: &str) -> Option<Params> {
+    let mut params = Vec::new();
+    let mut patterns = pattern.trim_start_matches('/').split('/');
+    let mut segments = path.trim_start_matches('/').split('/');
+    loop {
+        match (patterns.next(), segments.next()) {
+            (Some("*"), _) => return patterns.next().map_or(Some(Params(params)), |_| None),
+            (Some(pattern), Some(segment)) if pattern == segment => {}
+            (Some(pattern), Some(segment))
+                if pattern.starts_with('{') && pattern.ends_with('}') && !segment.is_empty() =>
+            {
+                params.push((
+                    pattern[1..pattern.len() - 1].to_string(),
+                    percent_decode_str(segment).decode_utf8_lossy().into_owned(),
+                ))
+            }
+            (None, None) => return Some(Params(params)),
+            _ => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{match_path, Params, Router};
+    use crate::http::{header::ALLOW, Method, Request, Response};
+    use crate::mock::{Client, Error};
+    use crate::HttpClient;
+    use futures::executor::block_on;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_match_path() {
+        assert!(match_path("/api/user/{id}", "/api/user/0").is_some());
+        assert!(match_path("api/user", "/api/user").is_some());
+        assert!(match_path("/api/user/{id}", "/api/user/").is_none());
+        assert!(match_path("/api/user/{id}", "/api/user/0/avatar").is_none());
+        assert!(match_path("/api/*", "/api/user/0/avatar").is_some());
+        assert!(match_path("/api/*", "/static/index.html").is_none());
+        let params = match_path("/api/user/{name}/{id}", "/api/user/hexi%20lee/0").unwrap();
+        assert_eq!(Some("hexi lee"), params.get("name"));
+        assert_eq!(Some("0"), params.get("id"));
+        assert_eq!(None, params.get("age"));
+    }
+
+    fn request(method: Method, path: &str, body: &[u8]) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .method(method)
+            .uri(format!("http://localhost{}", path))
+            .body(body.to_vec())?)
+    }
+
+    #[test]
+    fn crud() -> Result<(), Error> {
+        let store = Arc::new(Mutex::new(HashMap::<String, Vec<u8>>::new()));
+        let (get_store, put_store, delete_store) = (store.clone(), store.clone(), store.clone());
+        let router = Router::new()
+            .with_route(
+                Method::GET,
+                "/api/user/{id}",
+                move |req: Request<Vec<u8>>| {
+                    let id = Params::of(&req).get("id").unwrap().to_string();
+                    let user = get_store.lock().unwrap().get(&id).cloned();
+                    async move {
+                        Ok(match user {
+                            Some(user) => Response::new(user),
+                            None => Response::builder().status(404).body(Vec::new())?,
+                        })
+                    }
+                },
+            )
+            .with_route(
+                Method::PUT,
+                "/api/user/{id}",
+                move |req: Request<Vec<u8>>| {
+                    let id = Params::of(&req).get("id").unwrap().to_string();
+                    put_store.lock().unwrap().insert(id, req.into_body());
+                    async { Ok(Response::new(Vec::new())) }
+                },
+            )
+            .with_route(
+                Method::DELETE,
+                "/api/user/{id}",
+                move |req: Request<Vec<u8>>| {
+                    let id = Params::of(&req).get("id").unwrap().to_string();
+                    let status = match delete_store.lock().unwrap().remove(&id) {
+                        Some(_) => 204,
+                        None => 404,
+                    };
+                    async move { Ok(Response::builder().status(status).body(Vec::new())?) }
+                },
+            );
+        let client = Client::new("http://localhost".parse()?, move |req| router.handle(req));
+        block_on(async {
+            let get = || request(Method::GET, "/api/user/0", b"");
+            assert_eq!(404, client.request(get()?).await?.status());
+            let put = request(Method::PUT, "/api/user/0", b"hexi")?;
+            assert_eq!(200, client.request(put).await?.status());
+            assert_eq!(b"hexi", client.request(get()?).await?.body().as_slice());
+            let delete = || request(Method::DELETE, "/api/user/0", b"");
+            assert_eq!(204, client.request(delete()?).await?.status());
+            assert_eq!(404, client.request(delete()?).await?.status());
+            assert_eq!(404, client.request(get()?).await?.status());
+            Ok::<_, Error>(())
+        })?;
+        assert!(store.lock().unwrap().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn not_matched() -> Result<(), Error> {
+        let router = Router::new()
+            .with_route(Method::GET, "/api/user/{id}", |_| async {
+                Ok(Response::new(Vec::new()))
+            })
+            .with_route(Method::PUT, "/api/user/{id}", |_| async {
+                Ok(Response::new(Vec::new()))
+            });
+        block_on(async {
+            let resp = router
+                .handle(request(Method::GET, "/api/file/0", b"")?)
+                .await?;
+            assert_eq!(404, resp.status());
+            let resp = router
+                .handle(request(Method::DELETE, "/api/user/0", b"")?)
+                .await?;
+            assert_eq!(405, resp.status());
+            assert_eq!("GET, PUT", resp.headers().get(ALLOW).unwrap());
+            Ok(())
+        })
+    }
+}
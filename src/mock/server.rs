@@ -0,0 +1,623 @@
+use super::error::{Error, Result, Unmatched};
+use super::router::match_path;
+use crate::{
+    async_trait,
+    http::{
+        header::{HeaderName, CONTENT_TYPE},
+        HeaderMap, HeaderValue, Method, Request, Response, StatusCode,
+    },
+    mime::Mime,
+    url::{form_urlencoded, Url},
+    Helper, HttpClient, ToContent, ToContentError,
+};
+use std::sync::Mutex;
+use std::thread;
+
+/// A mock server serving stubs of expectations.
+///
+/// A request is served by the first matched expectation which is not exhausted.
+/// Unmatched requests fail by `Error::Unmatched`, with a diff to the closest expectation.
+///
+/// Call counts are verified when the server is dropped, unmatched requests are reported too,
+/// it can be disabled by `with_verify_on_drop(false)`.
+///
+/// ```rust
+/// use interfacer_http::mock::{Expectation, MockServer, Stub};
+/// use interfacer_http::http::{header::{ACCEPT, CONTENT_TYPE}, HeaderValue, Method, Request};
+/// use interfacer_http::HttpClient;
+/// use futures::executor::block_on;
+///
+/// let server = MockServer::new("https://mock.rs".parse().unwrap()).with_expectation(
+///     Expectation::new(Method::GET, "/api/user/{id}")
+///         .with_header(ACCEPT, HeaderValue::from_static("text/plain"))
+///         .with_times(1)
+///         .with_stub(
+///             Stub::new(200)
+///                 .with_header(CONTENT_TYPE, HeaderValue::from_static("text/plain"))
+///                 .with_body("hexi"),
+///         ),
+/// );
+/// let req = Request::get("https://mock.rs/api/user/0")
+///     .header(ACCEPT, "text/plain")
+///     .body(Vec::new())
+///     .unwrap();
+/// let resp = block_on(server.request(req)).unwrap();
+/// assert_eq!(b"hexi".to_vec(), resp.into_body());
+/// ```
+pub struct MockServer {
+    helper: Helper,
+    expectations: Vec<Expectation>,
+    calls: Mutex<Vec<usize>>,
+    unmatched: Mutex<Vec<Unmatched>>,
+    verify_on_drop: bool,
+}
+
+/// Expected request, matched by method, path pattern, queries, headers and body.
+///
+/// Segments like `{id}` in the path pattern match any single segment,
+/// a trailing `*` matches the rest of the path.
+#[derive(Debug, Clone)]
+pub struct Expectation {
+    method: Method,
+    path: String,
+    queries: Vec<(String, String)>,
+    headers: HeaderMap,
+    body: Option<BodyMatcher>,
+    times: Option<usize>,
+    stub: Stub,
+}
+
+#[derive(Debug, Clone)]
+enum BodyMatcher {
+    Bytes(Vec<u8>),
+    #[cfg(any(feature = "serde-full", feature = "serde-json"))]
+    Json(serde_json::Value),
+}
+
+/// Stubbed response of an `Expectation`.
+#[derive(Debug, Clone)]
+pub struct Stub {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl MockServer {
+    /// @param base_url: base url to join path
+    pub fn new(base_url: Url) -> Self {
+        Self {
+            helper: Helper::new().with_base_url(base_url),
+            expectations: Vec::new(),
+            calls: Mutex::new(Vec::new()),
+            unmatched: Mutex::new(Vec::new()),
+            verify_on_drop: true,
+        }
+    }
+
+    /// replace the helper, base url should be bound to `helper` again.
+    pub fn with_helper(mut self, helper: Helper) -> Self {
+        self.helper = helper;
+        self
+    }
+
+    /// verify call counts and unmatched requests on drop or not.
+    pub fn with_verify_on_drop(mut self, verify_on_drop: bool) -> Self {
+        self.verify_on_drop = verify_on_drop;
+        self
+    }
+
+    /// register an expectation.
+    pub fn with_expectation(mut self, expectation: Expectation) -> Self {
+        self.expectations.push(expectation);
+        self.calls.get_mut().unwrap().push(0);
+        self
+    }
+
+    /// call counts of expectations, in order of registration.
+    pub fn calls(&self) -> Vec<usize> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// check call counts and unmatched requests, they are checked on drop too.
+    ///
+    /// panics with every violation.
+    pub fn verify(&self) {
+        let mut violations = Vec::new();
+        for (expectation, calls) in self.expectations.iter().zip(self.calls()) {
+            match expectation.times {
+                Some(times) if times != calls => violations.push(format!(
+                    "`{}` is expected to be called {} times, but called {} times",
+                    expectation, times, calls
+                )),
+                None if calls == 0 => violations.push(format!("`{}` is never called", expectation)),
+                _ => (),
+            }
+        }
+        for unmatched in self.unmatched.lock().unwrap().iter() {
+            violations.push(unmatched.to_string());
+        }
+        if !violations.is_empty() {
+            panic!(
+                "mock server verification failed:\n{}",
+                violations.join("\n")
+            );
+        }
+    }
+
+    fn unmatched(&self, req: &Request<Vec<u8>>, matched: Vec<usize>) -> Unmatched {
+        let reasons = if !matched.is_empty() {
+            matched
+                .into_iter()
+                .map(|index| {
+                    format!(
+                        "`{}` is exhausted by {} calls",
+                        self.expectations[index],
+                        self.expectations[index].times.unwrap_or_default()
+                    )
+                })
+                .collect()
+        } else {
+            let closest = self
+                .expectations
+                .iter()
+                .map(|expectation| (expectation, expectation.diff(req)))
+                .min_by_key(|(_, diffs)| diffs.len());
+            match closest {
+                Some((expectation, diffs)) => {
+                    let mut reasons = vec![format!("closest expectation: `{}`", expectation)];
+                    reasons.extend(diffs);
+                    reasons
+                }
+                None => vec!["no expectation is registered".to_owned()],
+            }
+        };
+        Unmatched {
+            request: format!("{} {}", req.method(), req.uri()),
+            reasons,
+        }
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        if self.verify_on_drop && !thread::panicking() {
+            self.verify()
+        }
+    }
+}
+
+#[async_trait]
+impl HttpClient for MockServer {
+    type Err = Error;
+    async fn request(&self, req: Request<Vec<u8>>) -> Result<Response<Vec<u8>>> {
+        let matched = self
+            .expectations
+            .iter()
+            .enumerate()
+            .filter(|(_, expectation)| expectation.diff(&req).is_empty())
+            .map(|(index, _)| index)
+            .collect::<Vec<_>>();
+        let served = {
+            let mut calls = self.calls.lock().unwrap();
+            let served =
+                matched
+                    .iter()
+                    .cloned()
+                    .find(|index| match self.expectations[*index].times {
+                        Some(times) => calls[*index] < times,
+                        None => true,
+                    });
+            if let Some(index) = served {
+                calls[index] += 1;
+            }
+            served
+        };
+        match served {
+            Some(index) => self.expectations[index].stub.to_response(),
+            None => {
+                let unmatched = self.unmatched(&req, matched);
+                self.unmatched.lock().unwrap().push(unmatched.clone());
+                Err(unmatched.into())
+            }
+        }
+    }
+
+    fn helper(&self) -> &Helper {
+        &self.helper
+    }
+}
+
+impl Expectation {
+    /// Construct an expectation responding `200 OK` by default.
+    pub fn new(method: Method, path: impl Into<String>) -> Self {
+        Self {
+            method,
+            path: path.into(),
+            queries: Vec::new(),
+            headers: HeaderMap::new(),
+            body: None,
+            times: None,
+            stub: Stub::new(200),
+        }
+    }
+
+    /// expect a query pair.
+    pub fn with_query(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.queries.push((key.into(), value.into()));
+        self
+    }
+
+    /// expect a header value.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// expect the body.
+    pub fn with_body(self, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            body: Some(BodyMatcher::Bytes(body.into())),
+            ..self
+        }
+    }
+
+    /// expect the body equal to a json value, regardless of formatting and order of keys.
+    #[cfg(any(feature = "serde-full", feature = "serde-json"))]
+    pub fn with_json_body(self, body: serde_json::Value) -> Self {
+        Self {
+            body: Some(BodyMatcher::Json(body)),
+            ..self
+        }
+    }
+
+    /// expect to be called exactly `times`, at least once by default.
+    pub fn with_times(self, times: usize) -> Self {
+        Self {
+            times: Some(times),
+            ..self
+        }
+    }
+
+    /// respond the stub.
+    pub fn with_stub(self, stub: Stub) -> Self {
+        Self { stub, ..self }
+    }
+
+    /// differences between `req` and the expectation, empty if they match.
+    fn diff(&self, req: &Request<Vec<u8>>) -> Vec<String> {
+        let mut diffs = Vec::new();
+        if req.method() != self.method {
+            diffs.push(format!(
+                "method: expected `{}`, got `{}`",
+                self.method,
+                req.method()
+            ));
+        }
+        if match_path(&self.path, req.uri().path()).is_none() {
+            diffs.push(format!(
+                "path: expected `{}`, got `{}`",
+                self.path,
+                req.uri().path()
+            ));
+        }
+        let queries = form_urlencoded::parse(req.uri().query().unwrap_or("").as_bytes())
+            .into_owned()
+            .collect::<Vec<_>>();
+        for (key, value) in self.queries.iter() {
+            if !queries.iter().any(|(k, v)| k == key && v == value) {
+                let actual = queries
+                    .iter()
+                    .filter(|(k, _)| k == key)
+                    .map(|(_, v)| v.as_str())
+                    .collect::<Vec<_>>();
+                diffs.push(format!(
+                    "query {}: expected {:?}, got {:?}",
+                    key, value, actual
+                ));
+            }
+        }
+        for (name, value) in self.headers.iter() {
+            let values = req.headers().get_all(name);
+            if !values.iter().any(|actual| actual == value) {
+                diffs.push(format!(
+                    "header {}: expected {:?}, got {:?}",
+                    name,
+                    value,
+                    values.iter().collect::<Vec<_>>()
+                ));
+            }
+        }
+        if let Some(body) = &self.body {
+            if let Some((expected, actual)) = body.diff(req.body()) {
+                let mut lines = vec!["body:".to_owned()];
+                lines.extend(diff_lines(&expected, &actual));
+                diffs.push(lines.join("\n    "));
+            }
+        }
+        diffs
+    }
+}
+
+impl std::fmt::Display for Expectation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", self.method, self.path)
+    }
+}
+
+impl BodyMatcher {
+    /// expected and actual body to display if they don't match.
+    fn diff(&self, body: &[u8]) -> Option<(String, String)> {
+        let actual = String::from_utf8_lossy(body).into_owned();
+        match self {
+            BodyMatcher::Bytes(expected) if expected.as_slice() == body => None,
+            BodyMatcher::Bytes(expected) => {
+                Some((String::from_utf8_lossy(expected).into_owned(), actual))
+            }
+            #[cfg(any(feature = "serde-full", feature = "serde-json"))]
+            BodyMatcher::Json(expected) => {
+                let pretty = |value: &serde_json::Value| {
+                    serde_json::to_string_pretty(value).unwrap_or_default()
+                };
+                match serde_json::from_slice::<serde_json::Value>(body) {
+                    Ok(ref value) if value == expected => None,
+                    Ok(ref value) => Some((pretty(expected), pretty(value))),
+                    Err(_) => Some((pretty(expected), actual)),
+                }
+            }
+        }
+    }
+}
+
+/// line diff of expected and actual text, lines are prefixed by `- `, `+ ` or `  `.
+fn diff_lines(expected: &str, actual: &str) -> Vec<String> {
+    let expected = expected.lines().collect::<Vec<_>>();
+    let actual = actual.lines().collect::<Vec<_>>();
+    // lengths of longest common subsequences of suffixes.
+    let mut lcs = vec![vec![0usize; actual.len() + 1]; expected.len() + 1];
+    for i in (0..expected.len()).rev() {
+        for j in (0..actual.len()).rev() {
+            lcs[i][j] = if expected[i] == actual[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < expected.len() || j < actual.len() {
+        if i < expected.len() && j < actual.len() && expected[i] == actual[j] {
+            lines.push(format!("  {}", expected[i]));
+            i += 1;
+            j += 1;
+        } else if j == actual.len() || (i < expected.len() && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("- {}", expected[i]));
+            i += 1;
+        } else {
+            lines.push(format!("+ {}", actual[j]));
+            j += 1;
+        }
+    }
+    lines
+}
+
+impl Stub {
+    /// Construct a stub with empty body.
+    ///
+    /// panics if `status` is invalid.
+    pub fn new(status: u16) -> Self {
+        Self {
+            status: StatusCode::from_u16(status).expect("invalid status code"),
+            headers: HeaderMap::new(),
+            body: Vec::new(),
+        }
+    }
+
+    /// append a header.
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.append(name, value);
+        self
+    }
+
+    /// set the body.
+    pub fn with_body(self, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            body: body.into(),
+            ..self
+        }
+    }
+
+    /// set the body encoded from `content` and `Content-Type`.
+    pub fn with_content<T: ToContent>(
+        mut self,
+        content: &T,
+        content_type: &Mime,
+    ) -> std::result::Result<Self, ToContentError> {
+        let content_type = content.to_content_type(content_type);
+        self.body = content.to_content(&content_type)?;
+        if let Ok(value) = HeaderValue::from_str(content_type.as_ref()) {
+            self.headers.insert(CONTENT_TYPE, value);
+        }
+        Ok(self)
+    }
+
+    fn to_response(&self) -> Result<Response<Vec<u8>>> {
+        let mut resp = Response::builder()
+            .status(self.status)
+            .body(self.body.clone())?;
+        *resp.headers_mut() = self.headers.clone();
+        Ok(resp)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff_lines, Expectation, MockServer, Stub};
+    use crate::http::{
+        header::{ACCEPT, CONTENT_TYPE},
+        HeaderValue, Method, Request,
+    };
+    use crate::mock::Error;
+    use crate::HttpClient;
+    use futures::executor::block_on;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+
+    fn request(method: Method, uri: &str, body: &[u8]) -> Result<Request<Vec<u8>>, Error> {
+        Ok(Request::builder()
+            .method(method)
+            .uri(uri)
+            .header(ACCEPT, "application/json")
+            .body(body.to_vec())?)
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        assert_eq!(
+            vec!["  {", "-   \"age\": 20,", "+   \"age\": 21,", "  }"],
+            diff_lines("{\n  \"age\": 20,\n}", "{\n  \"age\": 21,\n}")
+        );
+    }
+
+    fn server() -> Result<MockServer, Error> {
+        Ok(MockServer::new("https://mock.rs".parse()?)
+            .with_expectation(
+                Expectation::new(Method::GET, "/api/user/{id}")
+                    .with_query("lang", "rust")
+                    .with_header(ACCEPT, HeaderValue::from_static("application/json"))
+                    .with_times(2)
+                    .with_stub(
+                        Stub::new(200)
+                            .with_header(CONTENT_TYPE, HeaderValue::from_static("text/plain"))
+                            .with_body("hexi"),
+                    ),
+            )
+            .with_expectation(
+                Expectation::new(Method::POST, "/api/user")
+                    .with_body("hexi")
+                    .with_stub(Stub::new(201)),
+            ))
+    }
+
+    #[test]
+    fn serve() -> Result<(), Error> {
+        let server = server()?;
+        for _ in 0..2 {
+            let resp = block_on(server.request(request(
+                Method::GET,
+                "https://mock.rs/api/user/0?lang=rust",
+                b"",
+            )?))?;
+            assert_eq!(200, resp.status());
+            assert_eq!("text/plain", resp.headers()[CONTENT_TYPE]);
+            assert_eq!(b"hexi".to_vec(), resp.into_body());
+        }
+        let resp =
+            block_on(server.request(request(Method::POST, "https://mock.rs/api/user", b"hexi")?))?;
+        assert_eq!(201, resp.status());
+        assert_eq!(vec![2, 1], server.calls());
+        Ok(())
+    }
+
+    #[test]
+    fn unmatched() -> Result<(), Error> {
+        let server = server()?.with_verify_on_drop(false);
+        match block_on(server.request(request(
+            Method::POST,
+            "https://mock.rs/api/user",
+            b"hexilee",
+        )?)) {
+            Err(Error::Unmatched(unmatched)) => {
+                assert_eq!(
+                    vec![
+                        "closest expectation: `POST /api/user`",
+                        "body:\n    - hexi\n    + hexilee"
+                    ],
+                    unmatched.reasons
+                );
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match block_on(server.request(request(Method::GET, "https://mock.rs/api/user/0", b"")?)) {
+            Err(Error::Unmatched(unmatched)) => {
+                assert_eq!(
+                    "query lang: expected \"rust\", got []",
+                    unmatched.reasons[1]
+                )
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        let result = catch_unwind(AssertUnwindSafe(|| server.verify()));
+        let message = result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message
+            .contains("`GET /api/user/{id}` is expected to be called 2 times, but called 0 times"));
+        assert!(message.contains("`POST /api/user` is never called"));
+        assert!(message.contains("unmatched request `POST https://mock.rs/api/user`"));
+        Ok(())
+    }
+
+    #[test]
+    fn exhausted() -> Result<(), Error> {
+        let server = MockServer::new("https://mock.rs".parse()?)
+            .with_verify_on_drop(false)
+            .with_expectation(Expectation::new(Method::GET, "/").with_times(1));
+        block_on(server.request(request(Method::GET, "https://mock.rs/", b"")?))?;
+        match block_on(server.request(request(Method::GET, "https://mock.rs/", b"")?)) {
+            Err(Error::Unmatched(unmatched)) => {
+                assert_eq!(vec!["`GET /` is exhausted by 1 calls"], unmatched.reasons)
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[cfg(any(feature = "serde-full", feature = "serde-json"))]
+    #[test]
+    fn json_body() -> Result<(), Error> {
+        let server = MockServer::new("https://mock.rs".parse()?)
+            .with_verify_on_drop(false)
+            .with_expectation(
+                Expectation::new(Method::PUT, "/api/user/{id}")
+                    .with_json_body(serde_json::json!({"name": "hexi", "age": 20}))
+                    .with_stub(
+                        Stub::new(200).with_content(&vec![0], &crate::mime::APPLICATION_JSON)?,
+                    ),
+            );
+        let resp = block_on(server.request(request(
+            Method::PUT,
+            "https://mock.rs/api/user/0",
+            br#"{"age":20,"name":"hexi"}"#,
+        )?))?;
+        assert_eq!(200, resp.status());
+        assert_eq!("application/json", resp.headers()[CONTENT_TYPE]);
+        assert_eq!(b"[0]".to_vec(), resp.into_body());
+        match block_on(server.request(request(
+            Method::PUT,
+            "https://mock.rs/api/user/0",
+            br#"{"age":21,"name":"hexi"}"#,
+        )?)) {
+            Err(Error::Unmatched(unmatched)) => assert_eq!(
+                vec![
+                    "closest expectation: `PUT /api/user/{id}`",
+                    concat!(
+                        "body:\n",
+                        "      {\n",
+                        "    -   \"age\": 20,\n",
+                        "    +   \"age\": 21,\n",
+                        "        \"name\": \"hexi\"\n",
+                        "      }"
+                    )
+                ],
+                unmatched.reasons
+            ),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test]
+    #[should_panic(expected = "is never called")]
+    fn verify_on_drop() {
+        let _server = MockServer::new("https://mock.rs".parse().unwrap())
+            .with_expectation(Expectation::new(Method::GET, "/"));
+    }
+}